@@ -1,47 +1,55 @@
 //! script used for distribution from dealer account on testnet. Test with
-//! `test_bulk_send` first
+//! `test_bulk_send` first. The node and chain ID come from the "onex" chain of
+//! `--network` in `tests/configs/networks.ron`, the original distribution was
+//! on the same node with `--network testnet --chain-id onex-testnet-3`.
 
 use std::{cmp::min, time::Duration};
 
+use clap::Parser;
 use common::{
     container_runner,
     contest::{get_txs, Record},
     get_private_key,
+    profiles::{ProfileArgs, ResolvedChain},
 };
 use deep_space::{Address, Coin};
 use log::info;
 use onomy_test_lib::{
     cosmovisor::{cosmovisor_get_addr, sh_cosmovisor},
     dockerfiles::dockerfile_onexd,
-    onomy_std_init,
     super_orchestrator::{
         stacked_errors::{ensure, ensure_eq, Error, Result, StackableErr},
-        Command, FileOptions,
+        std_init, Command, FileOptions,
     },
     Args, TIMEOUT,
 };
 use tokio::time::sleep;
 use u64_array_bigints::u256;
 
-const NODE: &str = "http://34.86.135.162:26657";
-const NODE_GRPC: &str = "http://34.86.135.162:9090";
-const CHAIN_ID: &str = "onex-testnet-3";
 const RECORDS_PATH: &str = "/resources/onex-trade-war-filtered.ron";
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let args = ProfileArgs::parse();
 
     Err(Error::from("do not comment out unless ready for send")).stack()?;
 
-    if let Some(ref s) = args.entry_name {
+    if let Some(ref s) = args.args.entry_name {
         match s.as_str() {
-            "onexd" => onexd_runner(&args).await,
+            "onexd" => onexd_runner(&args.args).await,
             _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
         }
     } else {
+        args.resolve("onex")
+            .await
+            .stack()?
+            .write_resolved()
+            .await
+            .stack()?;
         FileOptions::copy(
-            args.mnemonic_path
+            args.args
+                .mnemonic_path
                 .as_deref()
                 .stack_err(|| "need --mnemonic-path")?,
             "./tests/resources/tmp/mnemonic.txt",
@@ -49,7 +57,7 @@ async fn main() -> Result<()> {
         .await
         .stack()?;
 
-        container_runner(&args, &[("onexd", &dockerfile_onexd())])
+        container_runner(&args.args, &[("onexd", &dockerfile_onexd())])
             .await
             .stack()
     }
@@ -62,8 +70,11 @@ async fn onexd_runner(args: &Args) -> Result<()> {
         .await
         .stack()?;
 
-    sh_cosmovisor(["config node", NODE]).await.stack()?;
-    sh_cosmovisor(["config chain-id", CHAIN_ID]).await.stack()?;
+    let chain = ResolvedChain::load_resolved("onex").await.stack()?;
+    sh_cosmovisor(["config node", &chain.node]).await.stack()?;
+    sh_cosmovisor(["config chain-id", &chain.chain_id])
+        .await
+        .stack()?;
     sh_cosmovisor(["config keyring-backend test"])
         .await
         .stack()?;
@@ -82,7 +93,7 @@ async fn onexd_runner(args: &Args) -> Result<()> {
     info!("ADDR: {addr}");
     ensure_eq!(addr, "onomy1yks83spz6lvrrys8kh0untt22399tskk6jafcv");
 
-    let contact = deep_space::Contact::new(NODE_GRPC, TIMEOUT, "onomy").stack()?;
+    let contact = deep_space::Contact::new(&chain.grpc, TIMEOUT, &chain.prefix).stack()?;
     dbg!(contact.query_total_supply().await.stack()?);
 
     let private_key = get_private_key(&mnemonic).stack()?;
@@ -1,13 +1,26 @@
 //! Given a processed RON file from `process_contest_whitelist.rs`, this can
 //! create a contest genesis file
+//!
+//! By default a new `BaseAccount` and balance is pushed for every whitelisted
+//! address. With `--upsert`, addresses that already have an account are not
+//! given another one (existing vesting accounts are left untouched), the
+//! allocation is added to existing balances, and `bank.supply` is recomputed
+//! per denom.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use clap::Parser;
-use common::contest::Record;
+use common::{
+    contest::Record,
+    genesis::{
+        account_address, add_to_balance, coins_to_value, is_module_account, is_vesting_account,
+        recompute_supply,
+    },
+};
+use log::{info, warn};
 use onomy_test_lib::super_orchestrator::{
     stacked_errors::{Result, StackableErr},
-    stacked_get_mut, std_init, FileOptions,
+    stacked_get, stacked_get_mut, std_init, FileOptions,
 };
 use serde::ser::Serialize;
 use serde_json::{json, ser::PrettyFormatter, Serializer, Value};
@@ -21,6 +34,108 @@ struct Args {
     pub ron_input: String,
     #[arg(long, default_value_t = String::from("./tests/resources/genesis_output.json"))]
     pub genesis_output: String,
+    /// Merge into existing accounts and balances instead of blindly appending
+    #[arg(long)]
+    pub upsert: bool,
+}
+
+/// The allocation given to every whitelisted address
+fn allocation() -> BTreeMap<String, u128> {
+    [
+        ("abtc", 2000000000000000000),
+        ("anom", 1000000000000000000000),
+        ("aonex", 1000000000000000000000),
+        ("ausdc", 10000000000000000000000),
+        ("ausdt", 10000000000000000000000),
+        ("wei", 150000000000000000000),
+    ]
+    .into_iter()
+    .map(|(denom, amount)| (denom.to_owned(), amount))
+    .collect()
+}
+
+fn base_account(address: &str) -> Value {
+    json!(
+        {
+            "@type": "/cosmos.auth.v1beta1.BaseAccount",
+            "address": address,
+            "pub_key": null,
+            "account_number": "0",
+            "sequence": "0"
+        }
+    )
+}
+
+fn append(genesis: &mut Value, addresses: &BTreeSet<String>) -> Result<()> {
+    let allocation = coins_to_value(&allocation());
+    for address in addresses {
+        stacked_get_mut!(genesis["app_state"]["auth"]["accounts"])
+            .as_array_mut()
+            .stack()?
+            .push(base_account(address));
+        stacked_get_mut!(genesis["app_state"]["bank"]["balances"])
+            .as_array_mut()
+            .stack()?
+            .push(json!(
+                {
+                    "address": address,
+                    "coins": allocation
+                }
+            ));
+    }
+    Ok(())
+}
+
+fn upsert(genesis: &mut Value, addresses: &BTreeSet<String>) -> Result<()> {
+    let allocation = allocation();
+
+    let mut existing = BTreeMap::<String, Value>::new();
+    for account in stacked_get!(genesis["app_state"]["auth"]["accounts"])
+        .as_array()
+        .stack()?
+    {
+        let address = account_address(account).stack_err(|| "account without an address")?;
+        existing.insert(address.to_owned(), account.clone());
+    }
+
+    let mut num_new = 0;
+    let mut num_vesting = 0;
+    let mut num_existing_balances = 0;
+    for address in addresses {
+        match existing.get(address) {
+            Some(account) if is_module_account(account) => {
+                warn!("skipping module account {address}");
+                continue
+            }
+            Some(account) => {
+                // the allocation becomes a free balance on top of whatever is vesting
+                if is_vesting_account(account) {
+                    num_vesting += 1;
+                }
+            }
+            None => {
+                stacked_get_mut!(genesis["app_state"]["auth"]["accounts"])
+                    .as_array_mut()
+                    .stack()?
+                    .push(base_account(address));
+                num_new += 1;
+            }
+        }
+        if add_to_balance(genesis, address, &allocation).stack()? {
+            num_existing_balances += 1;
+        }
+    }
+    info!(
+        "{num_new} new accounts, {} existing accounts ({num_vesting} vesting), \
+         {num_existing_balances} existing balances were added to",
+        addresses.len() - num_new
+    );
+
+    let supply = recompute_supply(genesis).stack()?;
+    for (denom, amount) in supply {
+        info!("supply: {amount}{denom}");
+    }
+    Ok(())
 }
 
 #[tokio::main]
@@ -41,53 +156,10 @@ async fn main() -> Result<()> {
         base_accounts.insert(record.addr.clone());
     }
 
-    for address in base_accounts {
-        stacked_get_mut!(genesis["app_state"]["auth"]["accounts"])
-            .as_array_mut()
-            .stack()?
-            .push(json!(
-                {
-                    "@type": "/cosmos.auth.v1beta1.BaseAccount",
-                    "address": address,
-                    "pub_key": null,
-                    "account_number": "0",
-                    "sequence": "0"
-                }
-            ));
-        stacked_get_mut!(genesis["app_state"]["bank"]["balances"])
-            .as_array_mut()
-            .stack()?
-            .push(json!(
-                {
-                "address": address,
-                "coins": [
-                    {
-                      "denom": "abtc",
-                      "amount": "2000000000000000000"
-                    },
-                    {
-                      "denom": "anom",
-                      "amount": "1000000000000000000000"
-                    },
-                    {
-                      "denom": "aonex",
-                      "amount": "1000000000000000000000"
-                    },
-                    {
-                      "denom": "ausdc",
-                      "amount": "10000000000000000000000"
-                    },
-                    {
-                      "denom": "ausdt",
-                      "amount": "10000000000000000000000"
-                    },
-                    {
-                      "denom": "wei",
-                      "amount": "150000000000000000000"
-                    }
-                ]
-                }
-            ));
+    if args.upsert {
+        upsert(&mut genesis, &base_accounts).stack()?;
+    } else {
+        append(&mut genesis, &base_accounts).stack()?;
     }
 
     let mut genesis_s = vec![];
@@ -0,0 +1,225 @@
+//! Given the processed RON file from `process_contest_whitelist.rs` and a
+//! price table, this values every participant's portfolio after a contest and
+//! writes a ranked leaderboard
+//!
+//! Balances are queried from the `--chain` of `--network` in
+//! `tests/configs/networks.ron`, or read from `--balances-path` which can be
+//! an exported genesis, a JSON export of `query_accounts`, or a JSON array of
+//! `{"address": ..., "coins": [...]}` objects. `--grpc` overrides the gRPC
+//! endpoint of the chain.
+//!
+//! The price table is a RON `PriceTable` such as
+//! `(prices: {"abtc": 60000.0, "anom": 0.1, "wei": 3000.0}, decimals: {})`
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin contest_results -- --network testnet --chain onex --prices-path ./tests/resources/contest_prices.ron
+
+*/
+
+use std::{collections::BTreeMap, time::Duration};
+
+use clap::Parser;
+use common::{
+    accounts::AccountExport,
+    contest::{LeaderboardRow, PriceTable, Record},
+    genesis::parse_coins,
+    profiles::ProfileArgs,
+};
+use deep_space::{Address, Contact};
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    stacked_get, std_init, FileOptions,
+};
+use serde_json::Value;
+use tokio::time::sleep;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// The chain of `--network` to query balances from
+    #[arg(long, default_value_t = String::from("onex"))]
+    pub chain: String,
+    #[arg(long, default_value_t = String::from("./tests/resources/contest_whitelist.ron"))]
+    pub ron_input: String,
+    #[arg(long, default_value_t = String::from("./tests/resources/contest_prices.ron"))]
+    pub prices_path: String,
+    #[arg(long, default_value_t = String::from("./tests/resources/contest_leaderboard.csv"))]
+    pub leaderboard_output: String,
+    /// Exported genesis or balances JSON to use instead of querying `--chain`
+    #[arg(long)]
+    pub balances_path: Option<String>,
+    /// The maximum number of concurrent balance queries
+    #[arg(long, default_value_t = 16)]
+    pub concurrency: usize,
+    /// Retries for every balance query, with exponential backoff
+    #[arg(long, default_value_t = 5)]
+    pub retries: usize,
+}
+
+async fn get_balances(
+    contact: &Contact,
+    addr: &str,
+    retries: usize,
+) -> Result<BTreeMap<String, u128>> {
+    let mut delay = Duration::from_millis(500);
+    let mut attempt = 0;
+    loop {
+        let address = Address::from_bech32(addr.to_owned()).stack()?;
+        match contact.get_balances(address).await {
+            Ok(coins) => {
+                let mut balances = BTreeMap::new();
+                for coin in coins {
+                    balances.insert(coin.denom, coin.amount.try_resize_to_u128().stack()?);
+                }
+                return Ok(balances)
+            }
+            Err(e) => {
+                attempt += 1;
+                if attempt > retries {
+                    return Err(Error::from(format!(
+                        "failed to get balances of {addr} after {retries} retries: {e:?}"
+                    )))
+                }
+                warn!("retrying balances of {addr} after {e:?}");
+                sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Reads balances from an exported genesis, an `AccountExport`, or a JSON
+/// array of balances
+fn read_balances(balances: &Value) -> Result<BTreeMap<String, BTreeMap<String, u128>>> {
+    if balances.get("accounts").is_some() {
+        let export: AccountExport = serde_json::from_value(balances.clone())
+            .stack_err(|| "failed to parse the account export")?;
+        return Ok(export
+            .accounts
+            .into_iter()
+            .map(|account| (account.address, account.balances))
+            .collect())
+    }
+    let list = if balances.get("app_state").is_some() {
+        stacked_get!(balances["app_state"]["bank"]["balances"])
+            .as_array()
+            .stack()?
+    } else {
+        balances
+            .as_array()
+            .stack_err(|| "balances JSON should be a genesis or an array")?
+    };
+    let mut res = BTreeMap::new();
+    for balance in list {
+        let address = stacked_get!(balance["address"]).as_str().stack()?;
+        res.insert(
+            address.to_owned(),
+            parse_coins(stacked_get!(balance["coins"])).stack()?,
+        );
+    }
+    Ok(res)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = Args::parse();
+
+    let ron_input = FileOptions::read_to_string(&args.ron_input).await.stack()?;
+    let records: Vec<Record> = ron::from_str(&ron_input).stack()?;
+    let prices = FileOptions::read_to_string(&args.prices_path)
+        .await
+        .stack()?;
+    let prices: PriceTable = ron::from_str(&prices).stack()?;
+
+    let mut balances: BTreeMap<String, BTreeMap<String, u128>> = BTreeMap::new();
+    if let Some(ref balances_path) = args.balances_path {
+        let balances_s = FileOptions::read_to_string(balances_path).await.stack()?;
+        let all = read_balances(&serde_json::from_str(&balances_s).stack()?).stack()?;
+        for record in &records {
+            balances.insert(
+                record.addr.clone(),
+                all.get(&record.addr).cloned().unwrap_or_default(),
+            );
+        }
+    } else {
+        let chain = args
+            .profile_args
+            .resolve(&args.chain)
+            .await
+            .stack_err(|| "failed to resolve `--chain`, querying balances needs `--network`")?;
+        let contact = Contact::new(&chain.grpc, Duration::from_secs(30), &chain.prefix).stack()?;
+        let mut results = stream::iter(&records)
+            .map(|record| {
+                let contact = &contact;
+                async move {
+                    (
+                        record.addr.clone(),
+                        get_balances(contact, &record.addr, args.retries).await,
+                    )
+                }
+            })
+            .buffer_unordered(args.concurrency);
+        while let Some((addr, res)) = results.next().await {
+            balances.insert(addr, res.stack()?);
+            if (balances.len() % 100) == 0 {
+                info!("queried {} addresses", balances.len());
+            }
+        }
+    }
+
+    let mut missing_prices = BTreeMap::<&str, usize>::new();
+    let mut rows = vec![];
+    for record in &records {
+        let balance = &balances[&record.addr];
+        for denom in balance.keys() {
+            if !prices.prices.contains_key(denom) {
+                *missing_prices.entry(denom).or_default() += 1;
+            }
+        }
+        rows.push(LeaderboardRow {
+            rank: 0,
+            discord_user: record.discord_user.clone(),
+            addr: record.addr.clone(),
+            value: prices.value(balance),
+            balances: balance
+                .iter()
+                .map(|(denom, amount)| format!("{amount}{denom}"))
+                .collect::<Vec<_>>()
+                .join(","),
+        });
+    }
+    for (denom, count) in missing_prices {
+        warn!("no price for {denom}, it was valued at 0 in {count} portfolios");
+    }
+
+    rows.sort_by(|lhs, rhs| rhs.value.total_cmp(&lhs.value));
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.rank = i + 1;
+    }
+
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    for row in &rows {
+        wtr.serialize(row).stack()?;
+    }
+    let leaderboard_s = String::from_utf8(wtr.into_inner().stack()?).stack()?;
+    FileOptions::write_str(&args.leaderboard_output, &leaderboard_s)
+        .await
+        .stack()?;
+
+    for row in rows.iter().take(10) {
+        println!(
+            "{:>3} {:>16.2} {} {}",
+            row.rank, row.value, row.discord_user, row.addr
+        );
+    }
+
+    Ok(())
+}
@@ -2,41 +2,46 @@
 //! genesis, automatically overwriting the complete genesis (please commit in
 //! the --genesis-path directory before running, make an empty file if needed)
 //! with the partial genesis contents with consumer state inserted
+//!
+//! The provider node and the chain IDs come from the "onomy" and "onex" chains
+//! of `--network` in `tests/configs/networks.ron`. `--node` and `--chain-id`
+//! override the provider, and `--consumer-id` overrides the consumer chain ID.
 
 use onomy_test_lib::dockerfiles::dockerfile_onomyd;
 #[rustfmt::skip]
 /*
 e.x.
 
-cargo r --bin get_consumer_state -- --proposal-path ./../environments/testnet/onex-testnet-4/genesis-proposal.json --partial-genesis-path ./../environments/testnet/onex-testnet-4/partial-genesis.json --genesis-path ./../environments/testnet/onex-testnet-4/genesis.json --node http://34.145.158.212:26657
+cargo r --bin get_consumer_state -- --proposal-path ./../environments/testnet/onex-testnet-4/genesis-proposal.json --partial-genesis-path ./../environments/testnet/onex-testnet-4/partial-genesis.json --genesis-path ./../environments/testnet/onex-testnet-4/genesis.json --network testnet
 
 */
 
+use clap::Parser;
+use common::{
+    container_runner,
+    profiles::{ProfileArgs, ResolvedChain},
+};
 use onomy_test_lib::{
     cosmovisor::sh_cosmovisor,
-    onomy_std_init,
     super_orchestrator::{
         acquire_file_path,
-        docker::{Container, ContainerNetwork, Dockerfile},
-        sh,
         stacked_errors::{ensure_eq, Error, Result, StackableErr},
-        stacked_get, stacked_get_mut, FileOptions,
+        stacked_get, stacked_get_mut, std_init, FileOptions,
     },
-    yaml_str_to_json_value, Args, TIMEOUT,
+    yaml_str_to_json_value, Args,
 };
 use serde::ser::Serialize;
 use serde_json::{ser::PrettyFormatter, Serializer, Value};
 
-const ONOMY_CHAIN_ID: &str = "onomy-testnet-1";
-const CONSUMER_CHAIN_ID: &str = "onex-testnet-4";
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let profile_args = ProfileArgs::parse();
+    let args = &profile_args.args;
 
     if let Some(ref s) = args.entry_name {
         match s.as_str() {
-            "onomyd" => onomyd_runner(&args).await,
+            "onomyd" => onomyd_runner(args).await,
             _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
         }
     } else {
@@ -67,52 +72,28 @@ async fn main() -> Result<()> {
         .stack()?;
 
         // read from node
-        let logs_dir = "./tests/logs";
-        let resources_dir = "./tests/resources";
-        let dockerfiles_dir = "./tests/dockerfiles";
-        let bin_entrypoint = &args.bin_name;
-        let container_target = "x86_64-unknown-linux-gnu";
-
-        // build internal runner
-        sh([
-            "cargo build --release --bin",
-            bin_entrypoint,
-            "--target",
-            container_target,
-        ])
-        .await
-        .stack()?;
-
-        let mut containers = vec![];
-        containers.push(
-            Container::new("onomyd", Dockerfile::contents(dockerfile_onomyd()))
-                .external_entrypoint(
-                    format!("./target/{container_target}/release/{bin_entrypoint}"),
-                    [
-                        "--entry-name",
-                        "onomyd",
-                        "--node",
-                        args.node.as_deref().stack_err(|| "need --node")?,
-                    ],
-                )
-                .await
-                .stack()?,
-        );
-
-        let mut cn =
-            ContainerNetwork::new("test", containers, Some(dockerfiles_dir), true, logs_dir)
-                .stack()?;
-        cn.add_common_volumes([(logs_dir, "/logs"), (resources_dir, "/resources")]);
-        let uuid = cn.uuid_as_string();
-        cn.add_common_entrypoint_args(["--uuid", &uuid]);
-        cn.run_all(true).await.stack()?;
-        cn.wait_with_timeout_all(true, TIMEOUT).await.stack()?;
-        cn.terminate_all().await;
+        let provider = profile_args.resolve("onomy").await.stack()?;
+        let mut consumer = profile_args
+            .network_profile()
+            .await
+            .stack()?
+            .chain("onex")
+            .stack()?
+            .resolve("onex");
+        if let Some(ref consumer_id) = args.consumer_id {
+            consumer.chain_id = consumer_id.clone();
+        }
+        provider.write_resolved().await.stack()?;
+        consumer.write_resolved().await.stack()?;
+        container_runner(args, &[("onomyd", &dockerfile_onomyd())])
+            .await
+            .stack()?;
 
         // afterwards get the output and write the complete genesis
 
         let state_s = FileOptions::read_to_string(&format!(
-            "./tests/logs/{CONSUMER_CHAIN_ID}_ccvconsumer_state.json"
+            "./tests/logs/{}_ccvconsumer_state.json",
+            consumer.chain_id
         ))
         .await
         .stack()?;
@@ -132,23 +113,28 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn onomyd_runner(args: &Args) -> Result<()> {
+async fn onomyd_runner(_args: &Args) -> Result<()> {
     //let daemon_home = args.daemon_home.as_ref().stack()?;
+    let provider = ResolvedChain::load_resolved("onomy").await.stack()?;
+    let consumer = ResolvedChain::load_resolved("onex").await.stack()?;
 
     let proposal = FileOptions::read_to_string("/resources/tmp/proposal.json")
         .await
         .stack()?;
     let proposal: Value = serde_json::from_str(&proposal).stack()?;
-    ensure_eq!(stacked_get!(proposal["chain_id"]), CONSUMER_CHAIN_ID);
+    ensure_eq!(
+        stacked_get!(proposal["chain_id"]),
+        consumer.chain_id.as_str()
+    );
 
-    sh_cosmovisor(["config node", args.node.as_deref().stack()?])
+    sh_cosmovisor(["config node", &provider.node])
         .await
         .stack()?;
-    sh_cosmovisor(["config chain-id", ONOMY_CHAIN_ID])
+    sh_cosmovisor(["config chain-id", &provider.chain_id])
         .await
         .stack()?;
 
-    let ccvconsumer_state = sh_cosmovisor(["query provider consumer-genesis", CONSUMER_CHAIN_ID])
+    let ccvconsumer_state = sh_cosmovisor(["query provider consumer-genesis", &consumer.chain_id])
         .await
         .stack()?;
     let mut state = yaml_str_to_json_value(&ccvconsumer_state).stack()?;
@@ -167,7 +153,7 @@ async fn onomyd_runner(args: &Args) -> Result<()> {
     state.serialize(&mut ser).stack()?;
     let state_s = String::from_utf8(state_s).stack()?;
     FileOptions::write_str(
-        &format!("/logs/{CONSUMER_CHAIN_ID}_ccvconsumer_state.json"),
+        &format!("/logs/{}_ccvconsumer_state.json", consumer.chain_id),
         &state_s,
     )
     .await
@@ -1,94 +1,251 @@
-//! script used for testing the health of a grpc point
+//! Checks the latest block over the gRPC, CometBFT RPC `/status`, and REST
+//! `/cosmos/base/tendermint/v1beta1/blocks/latest` endpoints of nodes
 //!
-//! pass `--grpc` to the grpc point
+//! Pass any number of `--grpc`, `--rpc`, and `--rest` endpoints, and or
+//! `--network` to check all three endpoints of every chain in that network of
+//! `--networks-path`. The endpoints are checked concurrently, and the chain
+//! ID, height, block age, catching up status, and latency of each are printed.
+//! This exits with an error if any endpoint fails, is catching up, or is more
+//! than `--max-lag` blocks behind the best height seen for its chain ID.
+//!
+//! With `--watch`, the endpoints are instead checked every `--interval-secs`
+//! forever, and every check is appended as a JSON line to `--history-path`.
+//! After each round the uptime, average block time, and stalls in height
+//! progress of each endpoint over the last `--window-minutes` of the history
+//! are printed, including checks from earlier runs. This is the evidence to
+//! give to validators whose public endpoints we depend on. A stall is counted
+//! whenever two consecutive checks see the same height, so the interval should
+//! be longer than the block time.
 
+#[rustfmt::skip]
 /*
 e.x.
-cargo r --bin test_grpc_health -- --grpc http://34.145.158.212:9191
+
+cargo r --bin grpc_health -- --grpc http://34.145.158.212:9191 --rpc http://34.145.158.212:26657
+
+cargo r --bin grpc_health -- --network testnet
+
+cargo r --bin grpc_health -- --watch --interval-secs 60 --grpc http://34.145.158.212:9191
 
 */
 
-use deep_space::client::types::LatestBlock;
-use onomy_test_lib::{
-    dockerfiles::ONOMY_STD,
-    onomy_std_init,
-    super_orchestrator::{
-        docker::{Container, ContainerNetwork, Dockerfile},
-        sh,
-        stacked_errors::{Error, Result, StackableErr},
+use std::{path::Path, time::Duration};
+
+use chrono::Utc;
+use clap::Parser;
+use common::{
+    endpoint_health::{
+        best_heights, check_endpoints, network_endpoints, Endpoint, EndpointKind, EndpointStatus,
+        WindowStats,
     },
-    Args, TIMEOUT,
+    relayer_config::Network,
+    GREEN, RED, RESET,
 };
+use log::warn;
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init, FileOptions,
+};
+use tokio::{fs, io::AsyncWriteExt, time::sleep};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    /// gRPC endpoints, can be repeated
+    #[arg(long)]
+    pub grpc: Vec<String>,
+    /// CometBFT RPC endpoints, can be repeated
+    #[arg(long)]
+    pub rpc: Vec<String>,
+    /// REST (LCD) endpoints, can be repeated
+    #[arg(long)]
+    pub rest: Vec<String>,
+    /// Also check every chain of this network in `--networks-path`
+    #[arg(long)]
+    pub network: Option<String>,
+    #[arg(long, default_value_t = String::from("./tests/configs/networks.ron"))]
+    pub networks_path: String,
+    /// The address prefix for the `--grpc` endpoints
+    #[arg(long, default_value_t = String::from("onomy"))]
+    pub prefix: String,
+    /// Fail if an endpoint is more than this many blocks behind the best
+    /// height of its chain
+    #[arg(long, default_value_t = 5)]
+    pub max_lag: u64,
+    #[arg(long, default_value_t = 10)]
+    pub timeout_secs: u64,
+    /// Keep checking the endpoints and record the history
+    #[arg(long)]
+    pub watch: bool,
+    #[arg(long, default_value_t = 30)]
+    pub interval_secs: u64,
+    #[arg(long, default_value_t = String::from("./tests/logs/grpc_health_history.jsonl"))]
+    pub history_path: String,
+    /// The amount of history that the watch statistics are computed over
+    #[arg(long, default_value_t = 60 * 24)]
+    pub window_minutes: i64,
+}
+
+fn or_dash<T: ToString>(x: Option<T>) -> String {
+    x.map_or_else(|| "-".to_owned(), |x| x.to_string())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let args = Args::parse();
 
-    if let Some(ref s) = args.entry_name {
-        match s.as_str() {
-            "runner" => runner(&args).await,
-            _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
+    let mut endpoints = vec![];
+    for (kind, urls) in [
+        (EndpointKind::Grpc, &args.grpc),
+        (EndpointKind::Rpc, &args.rpc),
+        (EndpointKind::Rest, &args.rest),
+    ] {
+        for url in urls {
+            endpoints.push(Endpoint::new(kind, url, &args.prefix));
         }
+    }
+    if let Some(ref network) = args.network {
+        let network = Network::load(&args.networks_path, network).await.stack()?;
+        endpoints.extend(network_endpoints(&network));
+    }
+    if endpoints.is_empty() {
+        return Err(Error::from(
+            "no endpoints given, pass `--grpc`, `--rpc`, `--rest`, or `--network`",
+        ))
+    }
+
+    let timeout = Duration::from_secs(args.timeout_secs);
+    if args.watch {
+        return watch(&args, &endpoints, timeout).await
+    }
+
+    let statuses = check_endpoints(&endpoints, timeout).await.stack()?;
+    let problems = print_statuses(&statuses, args.max_lag);
+    if problems.is_empty() {
+        println!("{GREEN}all {} endpoints are healthy{RESET}", statuses.len());
+        Ok(())
     } else {
-        let logs_dir = "./tests/logs";
-        let resources_dir = "./tests/resources";
-        let dockerfiles_dir = "./tests/dockerfiles";
-        let bin_entrypoint = &args.bin_name;
-        let container_target = "x86_64-unknown-linux-gnu";
-
-        // build internal runner
-        sh([
-            "cargo build --release --bin",
-            bin_entrypoint,
-            "--target",
-            container_target,
-        ])
-        .await
-        .stack()?;
+        Err(Error::from(format!(
+            "unhealthy endpoints:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
 
-        let mut runner_args = vec!["--entry-name", "runner"];
-        // pass on these args to the test runner
-        if let Some(ref grpc) = args.grpc {
-            runner_args.push("--grpc");
-            runner_args.push(grpc);
+/// Prints the table of statuses and returns the problems
+fn print_statuses(statuses: &[EndpointStatus], max_lag: u64) -> Vec<String> {
+    let best = best_heights(statuses);
+    println!(
+        "{:<5} {:<40} {:<20} {:>10} {:>8} {:>11} {:>9}",
+        "kind", "url", "chain ID", "height", "age (s)", "catching up", "latency"
+    );
+    let mut problems = vec![];
+    for status in statuses {
+        let best_height = status
+            .chain_id
+            .as_ref()
+            .and_then(|chain_id| best.get(chain_id).copied());
+        let problem = status.problem(best_height, max_lag);
+        let color = if problem.is_some() { RED } else { GREEN };
+        println!(
+            "{color}{:<5} {:<40} {:<20} {:>10} {:>8} {:>11} {:>7}ms{RESET}",
+            status.kind,
+            status.url,
+            or_dash(status.chain_id.as_deref()),
+            or_dash(status.height),
+            or_dash(status.block_age().map(|age| age.num_seconds())),
+            or_dash(status.catching_up),
+            status.latency_ms,
+        );
+        problems.extend(problem);
+    }
+    problems
+}
+
+/// Loads the JSON lines history, lines that do not parse are skipped
+async fn load_history(history_path: &str) -> Result<Vec<EndpointStatus>> {
+    if !Path::new(history_path).exists() {
+        return Ok(vec![])
+    }
+    let history = FileOptions::read_to_string(history_path).await.stack()?;
+    let mut res = vec![];
+    for (i, line) in history.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue
         }
+        match serde_json::from_str(line) {
+            Ok(status) => res.push(status),
+            Err(e) => warn!("skipping line {} of {history_path}: {e}", i + 1),
+        }
+    }
+    Ok(res)
+}
 
-        let mut containers = vec![];
-        containers.push(
-            Container::new("runner", Dockerfile::contents(ONOMY_STD))
-                .external_entrypoint(
-                    format!("./target/{container_target}/release/{bin_entrypoint}"),
-                    runner_args,
-                )
-                .await
-                .stack()?,
-        );
+async fn append_history(history_path: &str, statuses: &[EndpointStatus]) -> Result<()> {
+    let mut lines = String::new();
+    for status in statuses {
+        lines.push_str(&serde_json::to_string(status).stack()?);
+        lines.push('\n');
+    }
+    if let Some(parent) = Path::new(history_path).parent() {
+        fs::create_dir_all(parent).await.stack()?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path)
+        .await
+        .stack_err(|| format!("could not open {history_path}"))?;
+    file.write_all(lines.as_bytes()).await.stack()?;
+    file.flush().await.stack()
+}
 
-        let mut cn =
-            ContainerNetwork::new("test", containers, Some(dockerfiles_dir), true, logs_dir)
-                .stack()?;
-        cn.add_common_volumes([(logs_dir, "/logs"), (resources_dir, "/resources")]);
-        let uuid = cn.uuid_as_string();
-        cn.add_common_entrypoint_args(["--uuid", &uuid]);
-        cn.run_all(true).await.stack()?;
-        cn.wait_with_timeout_all(true, TIMEOUT).await.stack()?;
-        cn.terminate_all().await;
-        Ok(())
+fn print_window_stats(history: &[EndpointStatus], endpoints: &[Endpoint], window_minutes: i64) {
+    println!("over the last {window_minutes} minutes:");
+    println!(
+        "{:<5} {:<40} {:>7} {:>8} {:>14} {:>7} {:>17}",
+        "kind", "url", "checks", "uptime", "block time (s)", "stalls", "longest stall (s)"
+    );
+    for endpoint in endpoints {
+        let endpoint_history: Vec<_> = history
+            .iter()
+            .filter(|status| (status.kind == endpoint.kind) && (status.url == endpoint.url))
+            .cloned()
+            .collect();
+        let stats = WindowStats::new(&endpoint_history);
+        let uptime = stats.uptime_percent();
+        let color = if stats.up == stats.checks { GREEN } else { RED };
+        println!(
+            "{color}{:<5} {:<40} {:>7} {:>7.2}% {:>14} {:>7} {:>17}{RESET}",
+            endpoint.kind,
+            endpoint.url,
+            stats.checks,
+            uptime,
+            or_dash(stats.avg_block_time.map(|time| format!("{time:.2}"))),
+            stats.stalls,
+            stats.longest_stall.num_seconds(),
+        );
     }
 }
 
-async fn runner(args: &Args) -> Result<()> {
-    let contact = deep_space::Contact::new(
-        args.grpc.as_deref().stack_err(|| "need `--grpc`")?,
-        TIMEOUT,
-        "onomy",
-    )
-    .stack()?;
-    let latest_block = contact.get_latest_block().await.stack()?;
-
-    match latest_block {
-        LatestBlock::Latest { block: _ } => Ok(()),
-        LatestBlock::Syncing { block: _ } => Ok(()),
-        LatestBlock::WaitingToStart => Ok(()),
+/// Checks the endpoints forever, see the module docs
+async fn watch(args: &Args, endpoints: &[Endpoint], timeout: Duration) -> Result<()> {
+    let window = chrono::Duration::minutes(args.window_minutes);
+    let mut history = load_history(&args.history_path).await.stack()?;
+    loop {
+        let statuses = check_endpoints(endpoints, timeout).await.stack()?;
+        for problem in print_statuses(&statuses, args.max_lag) {
+            warn!("{problem}");
+        }
+        append_history(&args.history_path, &statuses)
+            .await
+            .stack()?;
+        history.extend(statuses);
+        let cutoff = Utc::now() - window;
+        history.retain(|status| status.checked_at >= cutoff);
+        history.sort_by_key(|status| status.checked_at);
+        print_window_stats(&history, endpoints, args.window_minutes);
+        sleep(Duration::from_secs(args.interval_secs)).await;
     }
 }
@@ -0,0 +1,56 @@
+//! Serves a Prometheus `/metrics` endpoint with the health of every chain in
+//! `--network`: the latest height, block age, and syncing flag of each chain,
+//! the time left in the trusting period of each IBC client, and the relayer
+//! balances if `--mnemonic-path` is given.
+//!
+//! The ICS clients are discovered from the provider if they are not listed,
+//! see `tests/configs/networks.ron`. `test_health_exporter` tests the metrics
+//! against an in-process gRPC stub.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin health_exporter -- --network testnet --listen 0.0.0.0:9184 --mnemonic-path ./../testnet_dealer_mnemonic.txt
+
+curl http://127.0.0.1:9184/metrics
+
+*/
+
+use clap::Parser;
+use common::{health::serve_metrics, profiles::ProfileArgs, relayer_config::Network};
+use log::info;
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Result, StackableErr},
+    std_init, FileOptions,
+};
+use tokio::net::TcpListener;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    #[arg(long, default_value_t = String::from("0.0.0.0:9184"))]
+    pub listen: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = Args::parse();
+
+    let mut network = Network::from_args(&args.profile_args).await.stack()?;
+    network.resolve_ics_clients().await.stack()?;
+    // the relayer mnemonic, for exporting the relayer balances
+    let mnemonic = match args.profile_args.args.mnemonic_path {
+        Some(ref mnemonic_path) => Some(FileOptions::read_to_string(mnemonic_path).await.stack()?),
+        None => None,
+    };
+
+    let listener = TcpListener::bind(&args.listen)
+        .await
+        .stack_err(|| format!("could not listen on {}", args.listen))?;
+    info!("serving metrics on http://{}/metrics", args.listen);
+    serve_metrics(listener, network, mnemonic).await.stack()
+}
@@ -0,0 +1,175 @@
+//! Summarizes a Hermes log such as ./tests/logs/hermes_ics_runner.log, so that
+//! it does not need to be read through after every `hermes_manual` or
+//! `insure_relayers` run.
+//!
+//! Errors and warnings are grouped by the chain, client, and channel that they
+//! are about, successful client updates and relayed packets are counted, and
+//! lines matching known issues (expired clients, insufficient fees, account
+//! sequence mismatches, and RPC timeouts) are flagged. This exits with an error
+//! if any known issue is found or if there are more than `--max-errors` errors.
+
+/*
+e.x.
+
+cargo r --bin hermes_log_report -- --log-path ./tests/logs/hermes_ics_runner.log
+
+*/
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use common::{
+    hermes_log::{Level, LogLine},
+    GREEN, RED, RESET, YELLOW,
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init, FileOptions,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    #[arg(long, default_value_t = String::from("./tests/logs/hermes_ics_runner.log"))]
+    pub log_path: String,
+    /// Fail if there are more errors than this, not counting known issues which
+    /// always fail
+    #[arg(long, default_value_t = 0)]
+    pub max_errors: usize,
+    /// The number of chain/client/channel groups to print
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GroupCounts {
+    errors: usize,
+    warnings: usize,
+}
+
+#[derive(Debug, Clone)]
+struct IssueCounts {
+    count: usize,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+    example: String,
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(|| "?".to_owned(), |time| time.to_rfc3339())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = Args::parse();
+
+    let log = FileOptions::read_to_string(&args.log_path).await.stack()?;
+
+    let mut lines = 0;
+    let mut errors = 0;
+    let mut warnings = 0;
+    let mut client_updates = 0;
+    let mut received_packets = 0;
+    let mut acknowledged_packets = 0;
+    let mut timed_out_packets = 0;
+    let mut groups: BTreeMap<(String, String, String), GroupCounts> = BTreeMap::new();
+    let mut issues: BTreeMap<&'static str, IssueCounts> = BTreeMap::new();
+    for line in log.lines() {
+        let line = match LogLine::parse(line) {
+            Some(line) => line,
+            None => continue,
+        };
+        lines += 1;
+        if line.is_client_update() {
+            client_updates += 1;
+        }
+        received_packets += line.count_event("WriteAcknowledgement");
+        acknowledged_packets += line.count_event("AcknowledgePacket");
+        timed_out_packets += line.count_event("TimeoutPacket");
+        if line.level < Level::Warn {
+            continue
+        }
+
+        let group = groups
+            .entry((
+                line.chain().unwrap_or("-").to_owned(),
+                line.client().unwrap_or("-").to_owned(),
+                line.channel().unwrap_or("-").to_owned(),
+            ))
+            .or_default();
+        if line.level == Level::Error {
+            errors += 1;
+            group.errors += 1;
+        } else {
+            warnings += 1;
+            group.warnings += 1;
+        }
+
+        if let Some(issue) = line.known_issue() {
+            let counts = issues.entry(issue.name).or_insert_with(|| IssueCounts {
+                count: 0,
+                first: line.timestamp,
+                last: None,
+                example: line.message.chars().take(200).collect(),
+            });
+            counts.count += 1;
+            counts.last = line.timestamp;
+        }
+    }
+
+    println!(
+        "{}: {lines} lines, {errors} errors, {warnings} warnings",
+        args.log_path
+    );
+    println!(
+        "client updates: {client_updates}, received packets: {received_packets}, acknowledged \
+         packets: {acknowledged_packets}, timed out packets: {timed_out_packets}"
+    );
+
+    if !groups.is_empty() {
+        println!("errors and warnings by chain, client, and channel:");
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_by_key(|(_, counts)| std::cmp::Reverse((counts.errors, counts.warnings)));
+        for ((chain, client, channel), counts) in groups.iter().take(args.top) {
+            let color = if counts.errors > 0 { RED } else { YELLOW };
+            println!(
+                "{color}  {:<20} {:<20} {:<12} {:>6} errors {:>6} warnings{RESET}",
+                chain, client, channel, counts.errors, counts.warnings
+            );
+        }
+        if groups.len() > args.top {
+            println!("  ... and {} more", groups.len() - args.top);
+        }
+    }
+
+    let mut problems = vec![];
+    for (name, counts) in &issues {
+        println!(
+            "{RED}{name}: {} times, first at {}, last at {}{RESET}\n    e.x. {}",
+            counts.count,
+            format_time(counts.first),
+            format_time(counts.last),
+            counts.example
+        );
+        problems.push(format!("{name} ({} times)", counts.count));
+    }
+    if errors > args.max_errors {
+        problems.push(format!(
+            "{errors} errors, more than the maximum of {}",
+            args.max_errors
+        ));
+    }
+
+    if problems.is_empty() {
+        println!("{GREEN}no problems found{RESET}");
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "problems found in {}:\n{}",
+            args.log_path,
+            problems.join("\n")
+        )))
+    }
+}
@@ -1,180 +1,64 @@
 //! Run this manually once a week to insure that ICS channels do not expire
 //!
 //! Check the outputs in the cosole and in ./tests/logs/hermes_ics_runner.log to
-//! make sure there are no errors or significant warnings
+//! make sure there are no errors or significant warnings, `hermes_log_report`
+//! can summarize the log
+//!
+//! This relays between every chain of `--network` in
+//! `tests/configs/networks.ron`, only over the `channels` of chains that list
+//! them.
 
+#[rustfmt::skip]
 /*
 e.x.
 
-cargo r --bin hermes_manual -- --mnemonic-path ./../testnet_dealer_mnemonic.txt
+cargo r --bin hermes_manual -- --mnemonic-path ./../testnet_dealer_mnemonic.txt --network mainnet-osmosis
 
 cargo r --bin auto_exec_i -- --container-name hermes
 
 */
 
+use clap::Parser;
+use common::{
+    hermes_config::{HermesConfig, PacketFilter},
+    relayer_config::{Network, RelayerArgs},
+};
 use onomy_test_lib::{
     dockerfiles::dockerfile_hermes,
     hermes::{hermes_start, sh_hermes},
-    onomy_std_init,
     super_orchestrator::{
         docker::{Container, ContainerNetwork, Dockerfile},
         sh,
         stacked_errors::{Error, Result, StackableErr},
-        FileOptions,
+        std_init, FileOptions,
     },
-    Args, TIMEOUT,
+    TIMEOUT,
 };
 use tokio::time::sleep;
 
-#[rustfmt::skip]
-const HERMES_CONFIG: &str = r##"
-# The global section has parameters that apply globally to the relayer operation.
-[global]
-log_level = 'info'
-
-# Specify the mode to be used by the relayer. [Required]
-[mode]
-
-# Specify the client mode.
-[mode.clients]
-
-# Whether or not to enable the client workers. [Required]
-enabled = true
-
-# Whether or not to enable periodic refresh of clients. [Default: true]
-# This feature only applies to clients that underlie an open channel.
-# For Tendermint clients, the frequency at which Hermes refreshes them is 2/3 of their
-# trusting period (e.g., refresh every ~9 days if the trusting period is 14 days).
-# Note: Even if this is disabled, clients will be refreshed automatically if
-#      there is activity on a connection or channel they are involved with.
-refresh = true
-
-# Whether or not to enable misbehaviour detection for clients. [Default: true]
-misbehaviour = false
-
-# Specify the connections mode.
-[mode.connections]
-
-# Whether or not to enable the connection workers for handshake completion. [Required]
-enabled = false
-
-# Specify the channels mode.
-[mode.channels]
-
-# Whether or not to enable the channel workers for handshake completion. [Required]
-enabled = false
-
-# Specify the packets mode.
-[mode.packets]
-
-# Whether or not to enable the packet workers. [Required]
-enabled = true
-
-# Parametrize the periodic packet clearing feature.
-# Interval (in number of blocks) at which pending packets
-# should be periodically cleared. A value of '0' will disable
-# periodic packet clearing. [Default: 100]
-clear_interval = 0
-
-# Whether or not to clear packets on start. [Default: true]
-clear_on_start = true
-
-# Toggle the transaction confirmation mechanism.
-# The tx confirmation mechanism periodically queries the `/tx_search` RPC
-# endpoint to check that previously-submitted transactions
-# (to any chain in this config file) have been successfully delivered.
-# If they have not been, and `clear_interval = 0`, then those packets are
-# queued up for re-submission.
-# If set to `false`, the following telemetry metrics will be disabled:
-# `acknowledgment_packets_confirmed`, `receive_packets_confirmed` and `timeout_packets_confirmed`.
-# [Default: false]
-tx_confirmation = false
-
-# Auto register the counterparty payee on a destination chain to
-# the relayer's address on the source chain. This can be used
-# for simple configuration of the relayer to receive fees for
-# relaying RecvPacket on fee-enabled channels.
-# For more complex configuration, turn this off and use the CLI
-# to manually register the payee addresses.
-# [Default: false]
-auto_register_counterparty_payee = false
-
-# The REST section defines parameters for Hermes' built-in RESTful API.
-# https://hermes.informal.systems/rest.html
-[rest]
-
-# Whether or not to enable the REST service. Default: false
-enabled = true
-
-# Specify the IPv4/6 host over which the built-in HTTP server will serve the RESTful
-# API requests. Default: 127.0.0.1
-host = '127.0.0.1'
-
-# Specify the port over which the built-in HTTP server will serve the restful API
-# requests. Default: 3000
-port = 3000
-
-
-# The telemetry section defines parameters for Hermes' built-in telemetry capabilities.
-# https://hermes.informal.systems/telemetry.html
-[telemetry]
-
-# Whether or not to enable the telemetry service. Default: false
-enabled = false
-
-# Specify the IPv4/6 host over which the built-in HTTP server will serve the metrics
-# gathered by the telemetry service. Default: 127.0.0.1
-host = '127.0.0.1'
-
-# Specify the port over which the built-in HTTP server will serve the metrics gathered
-# by the telemetry service. Default: 3001
-port = 3001
-
-[[chains]]
-id = 'onomy-mainnet-1'
-rpc_addr = 'http://34.28.250.29:26657'
-grpc_addr = 'http://34.28.250.29:9100'
-event_source = { mode = 'push', url = 'ws://34.28.250.29:26657/websocket', batch_delay = '200ms' }
-rpc_timeout = '15s'
-account_prefix = 'onomy'
-key_name = 'onomy-mainnet-1'
-store_prefix = 'ibc'
-gas_price = { price = 0, denom = 'anom' }
-max_gas = 10000000
-clock_drift = '5s'
-trusting_period = '7days'
-trust_threshold = { numerator = '1', denominator = '3' }
-
-[[chains]]
-id = 'osmosis-1'
-rpc_addr = 'https://osmosis-rpc.w3coins.io'
-grpc_addr = 'http://osmosis-grpc.w3coins.io:12590'
-event_source = { mode = 'push', url = 'ws://osmosis-rpc.w3coins.io/websocket', batch_delay = '200ms' }
-rpc_timeout = '15s'
-account_prefix = 'osmo'
-key_name = 'osmosis-1'
-store_prefix = 'ibc'
-gas_price = { price = 0.025, denom = 'uosmo' }
-max_gas = 10000000
-clock_drift = '5s'
-trusting_period = '7days'
-trust_threshold = { numerator = '1', denominator = '3' }
-
-[chains.packet_filter]
-policy = 'allow'
-list = [[
-    'transfer',
-    'channel-525',
-]]
-"##;
+/// The Hermes config of the network, with the packets of chains that list
+/// `channels` filtered to those channels
+fn hermes_config(network: &Network) -> HermesConfig {
+    let mut config = network.hermes_config();
+    for (chain, profile) in config.chains.iter_mut().zip(&network.chains) {
+        if !profile.channels.is_empty() {
+            chain.packet_filter = Some(PacketFilter {
+                policy: "allow".to_owned(),
+                list: profile.channels.clone(),
+            });
+        }
+    }
+    config
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let args = RelayerArgs::parse();
 
-    if let Some(ref s) = args.entry_name {
+    if let Some(ref s) = args.profile_args.args.entry_name {
         match s.as_str() {
-            "hermes" => hermes_runner(&args).await,
+            "hermes" => hermes_runner().await,
             _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
         }
     } else {
@@ -182,7 +66,8 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn container_runner(args: &Args) -> Result<()> {
+async fn container_runner(relayer_args: &RelayerArgs) -> Result<()> {
+    let args = &relayer_args.profile_args.args;
     let logs_dir = "./tests/logs";
     let dockerfiles_dir = "./tests/dockerfiles";
     let bin_entrypoint = &args.bin_name;
@@ -198,14 +83,22 @@ async fn container_runner(args: &Args) -> Result<()> {
     .await
     .stack()?;
 
-    FileOptions::copy(
-        args.mnemonic_path
-            .as_deref()
-            .stack_err(|| "need --mnemonic-path")?,
-        "./tests/resources/tmp/mnemonic.txt",
-    )
-    .await
-    .stack()?;
+    let network = relayer_args.network().await.stack()?;
+    network.write_resolved().await.stack()?;
+
+    let mnemonic_path = args
+        .mnemonic_path
+        .as_deref()
+        .stack_err(|| "need --mnemonic-path")?;
+    // make sure the relayer can pay for client updates before starting
+    let mnemonic = FileOptions::read_to_string(mnemonic_path).await.stack()?;
+    network
+        .check_wallets(&mnemonic, relayer_args)
+        .await
+        .stack()?;
+    FileOptions::copy(mnemonic_path, "./tests/resources/tmp/mnemonic.txt")
+        .await
+        .stack()?;
 
     let entrypoint = &format!("./target/{container_target}/release/{bin_entrypoint}");
 
@@ -227,11 +120,10 @@ async fn container_runner(args: &Args) -> Result<()> {
     let uuid = cn.uuid_as_string();
     cn.add_common_entrypoint_args(["--uuid", &uuid]);
 
-    FileOptions::write_str(
-        &format!("{dockerfiles_dir}/dockerfile_resources/__tmp_hermes_config.toml"),
-        HERMES_CONFIG,
-    )
-    .await?;
+    hermes_config(&network)
+        .write(&format!("{dockerfiles_dir}/dockerfile_resources"))
+        .await
+        .stack()?;
 
     cn.run_all(true).await.stack()?;
     cn.wait_with_timeout_all(true, TIMEOUT).await.stack()?;
@@ -239,7 +131,8 @@ async fn container_runner(args: &Args) -> Result<()> {
     Ok(())
 }
 
-async fn hermes_runner(_args: &Args) -> Result<()> {
+async fn hermes_runner() -> Result<()> {
+    let network = Network::load_resolved().await.stack()?;
     let mnemonic = FileOptions::read_to_string("/resources/tmp/mnemonic.txt")
         .await
         .stack()?;
@@ -250,10 +143,10 @@ async fn hermes_runner(_args: &Args) -> Result<()> {
         .stack()?;
 
     // add the chains
-    for id in ["onomy-mainnet-1", "osmosis-1"] {
+    for chain in hermes_config(&network).chains {
         sh_hermes([format!(
             "keys add --chain {} --mnemonic-file /root/.hermes/dealer_mnemonic.txt",
-            id
+            chain.id
         )])
         .await
         .stack()?;
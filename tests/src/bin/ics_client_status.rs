@@ -0,0 +1,148 @@
+//! Checks how close the ICS clients of `--network` in
+//! `tests/configs/networks.ron` are to expiring. For every chain and client ID,
+//! the client state and latest consensus state are queried over gRPC to find
+//! the time remaining in the trusting period, and the time until the
+//! provider's VSC timeout would deregister the consumer.
+//!
+//! This exits with an error if any client is below the thresholds, so that it
+//! can be run from a cron job.
+
+/*
+e.x.
+
+cargo r --bin ics_client_status -- --network testnet --min-trusting-hours 96
+
+*/
+
+use chrono::Utc;
+use clap::Parser;
+use common::{
+    ibc::{format_duration, query_client_status},
+    profiles::ProfileArgs,
+    relayer_config::Network,
+    GREEN, RED, RESET, YELLOW,
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// Fail if any client has less than this many hours left in its trusting
+    /// period
+    #[arg(long, default_value_t = 72)]
+    pub min_trusting_hours: i64,
+    /// The `vsc_timeout_period` of the provider
+    #[arg(long, default_value_t = 35)]
+    pub vsc_timeout_days: i64,
+    /// Fail if any client has less than this many days until the VSC timeout
+    #[arg(long, default_value_t = 14)]
+    pub min_vsc_days: i64,
+}
+
+/// Red if below `min`, yellow if below twice `min`, and green otherwise
+fn color(remaining: chrono::Duration, min: chrono::Duration) -> &'static str {
+    if remaining < min {
+        RED
+    } else if remaining < (min * 2) {
+        YELLOW
+    } else {
+        GREEN
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = Args::parse();
+
+    let mut network = Network::from_args(&args.profile_args).await.stack()?;
+    network.resolve_ics_clients().await.stack()?;
+    if network.chains.iter().all(|chain| chain.clients.is_empty()) {
+        return Err(Error::from(format!(
+            "no clients to check in network {}",
+            args.profile_args.network.as_deref().unwrap_or_default()
+        )))
+    }
+    let min_trusting = chrono::Duration::hours(args.min_trusting_hours);
+    let vsc_timeout = chrono::Duration::days(args.vsc_timeout_days);
+    let min_vsc = chrono::Duration::days(args.min_vsc_days);
+
+    let now = Utc::now();
+    let mut problems = vec![];
+    println!(
+        "{:<18} {:<20} {:<18} {:>12} {:>14} {:>14} {:>14}",
+        "host chain",
+        "client",
+        "counterparty",
+        "height",
+        "last update",
+        "trusting left",
+        "vsc left"
+    );
+    for chain in &network.chains {
+        for client_id in &chain.clients {
+            let status =
+                match query_client_status(&chain.chain_id, &chain.grpc_addr(), client_id).await {
+                    Ok(status) => status,
+                    Err(e) => {
+                        println!(
+                            "{RED}{:<18} {:<20} query failed: {e:?}{RESET}",
+                            chain.chain_id, client_id
+                        );
+                        problems.push(format!("{} {client_id}: query failed", chain.chain_id));
+                        continue
+                    }
+                };
+            let trusting_remaining = status.trusting_remaining(now).stack()?;
+            let vsc_remaining = (status.last_update + vsc_timeout) - now;
+            println!(
+                "{:<18} {:<20} {:<18} {:>12} {:>14} {}{:>14}{RESET} {}{:>14}{RESET}{}",
+                status.host_chain_id,
+                status.client_id,
+                status.counterparty_chain_id,
+                format!("{}-{}", status.revision_number, status.revision_height),
+                format_duration(now - status.last_update),
+                color(trusting_remaining, min_trusting),
+                format_duration(trusting_remaining),
+                color(vsc_remaining, min_vsc),
+                format_duration(vsc_remaining),
+                if status.frozen {
+                    format!(" {RED}FROZEN{RESET}")
+                } else {
+                    String::new()
+                },
+            );
+            if status.frozen {
+                problems.push(format!("{} {client_id}: frozen", chain.chain_id));
+            }
+            if trusting_remaining < min_trusting {
+                problems.push(format!(
+                    "{} {client_id}: {} left in the trusting period",
+                    chain.chain_id,
+                    format_duration(trusting_remaining)
+                ));
+            }
+            if vsc_remaining < min_vsc {
+                problems.push(format!(
+                    "{} {client_id}: {} left until the VSC timeout",
+                    chain.chain_id,
+                    format_duration(vsc_remaining)
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "clients below thresholds:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
@@ -1,44 +1,489 @@
-//! script for initiating ICS channels, please selectively comment and uncomment
-//! things as needed because usually there is an error somewhere that needs to
-//! be manually stepped through or skipped.
+//! script for initiating ICS channels. The handshake is split into steps (see
+//! `Step`) that are each skipped if the chains show that they have already
+//! completed, so a run that failed in the middle can simply be rerun. The
+//! results of every step (connection and channel IDs) are saved to
+//! `./tests/logs/{consumer_id}_ics_handshake.ron`. If a step needs to be
+//! manually stepped through, `--only-step` or `--from-step` can be used to
+//! resume afterwards.
 //!
 //! This can also just be used to run a relayer locally temporarily (note that
 //! it is relaying everything by default, may need to do some changes to
 //! hermes.rs)
 //!
 //! Look at hermes_ics_runner.log for output from the runner
+//!
+//! The provider and the consumer selected with `--consumer-id` (which can be
+//! left out if there is only one) come from `--network` in `--networks-path`.
+//! The ICS clients are discovered from the provider if they are not listed.
 
+#[rustfmt::skip]
 /*
 e.x.
 
-cargo r --bin init_ics_channels -- --mnemonic-path ./../testnet_dealer_mnemonic.txt
+cargo r --bin init_ics_channels -- --mnemonic-path ./../testnet_dealer_mnemonic.txt --network testnet --consumer-id onex-testnet-4
+
+cargo r --bin init_ics_channels -- --mnemonic-path ./../testnet_dealer_mnemonic.txt --network testnet --consumer-id onex-testnet-4 --from-step transfer-ack
 
 */
 
+use std::collections::BTreeSet;
+
+use clap::{Parser, ValueEnum};
+use common::{
+    ibc::{
+        query_channel, query_client_connections, query_connection, query_connection_channels,
+        ChannelInfo,
+    },
+    ics::{discover_ics_clients, IcsClients},
+    profiles::ChainProfile,
+    relayer_config::{Network, RelayerArgs},
+};
+use cosmos_sdk_proto::ibc::core::{
+    channel::v1::State as ChannelState, connection::v1::State as ConnectionState,
+};
+use log::info;
 use onomy_test_lib::{
     dockerfiles::dockerfile_hermes,
-    hermes::{hermes_start, sh_hermes, write_hermes_config, HermesChainConfig},
-    onomy_std_init,
+    hermes::{create_channel_pair, create_connection_pair, hermes_start, sh_hermes},
     super_orchestrator::{
         docker::{Container, ContainerNetwork, Dockerfile},
         sh,
         stacked_errors::{Error, Result, StackableErr},
-        FileOptions,
+        std_init, FileOptions,
     },
-    Args, TIMEOUT,
+    TIMEOUT,
 };
+use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
-const ONOMY_NODE: &str = "34.145.158.212";
-const CONSUMER_NODE: &str = "34.86.135.162";
-const ONOMY_CHAIN_ID: &str = "onomy-testnet-1";
-const CONSUMER_CHAIN_ID: &str = "onex-testnet-4";
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct InitArgs {
+    #[command(flatten)]
+    relayer_args: RelayerArgs,
+    /// Skip the steps before this one
+    #[arg(long, value_enum)]
+    from_step: Option<Step>,
+    /// Only run this step
+    #[arg(long, value_enum, conflicts_with = "from_step")]
+    only_step: Option<Step>,
+}
+
+/// The steps of the handshake, in order. Note that a client pair already
+/// exists because of the ICS setup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+enum Step {
+    /// Create the connection pair that is used for both ICS and IBC transfers,
+    /// or finish the handshake of a connection that was interrupted
+    Connection,
+    /// Create the ordered channel between the "consumer" and "provider" ports,
+    /// after which the consumer initializes a transfer channel by itself, or
+    /// `chan-open-confirm` a channel that was interrupted after the ack
+    CcvChannel,
+    /// `chan-open-try` of the transfer channel on the provider
+    TransferTry,
+    /// `chan-open-ack` of the transfer channel on the consumer
+    TransferAck,
+    /// `chan-open-confirm` of the transfer channel on the provider
+    TransferConfirm,
+}
+
+const STEPS: [Step; 5] = [
+    Step::Connection,
+    Step::CcvChannel,
+    Step::TransferTry,
+    Step::TransferAck,
+    Step::TransferConfirm,
+];
+
+impl Step {
+    /// The name used by `--from-step` and `--only-step`
+    fn name(self) -> String {
+        self.to_possible_value().unwrap().get_name().to_owned()
+    }
+}
+
+/// How far the handshake of a connection that was started on the consumer got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ConnectionProgress {
+    /// `conn-init` on the consumer, `conn-try` on the provider is next
+    Init,
+    /// `conn-try` on the provider, `conn-ack` on the consumer is next
+    TryOpen,
+    /// `conn-ack` on the consumer, `conn-confirm` on the provider is next
+    Ack,
+}
+
+/// The results of the handshake steps. Everything is redetected from the chains
+/// before every step, the saved IDs are only used to pick between multiple
+/// candidates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HandshakeState {
+    provider_chain_id: String,
+    consumer_chain_id: String,
+    consumer_connection: Option<String>,
+    provider_connection: Option<String>,
+    consumer_ccv_channel: Option<String>,
+    provider_ccv_channel: Option<String>,
+    consumer_transfer_channel: Option<String>,
+    provider_transfer_channel: Option<String>,
+    /// Set if the connection handshake was interrupted
+    #[serde(default)]
+    connection_progress: Option<ConnectionProgress>,
+    /// Set if the CCV channel handshake was interrupted after `chan-open-ack`
+    /// on the consumer, `chan-open-confirm` on the provider is next
+    #[serde(default)]
+    ccv_confirm_pending: bool,
+    completed: BTreeSet<Step>,
+}
+
+impl HandshakeState {
+    fn path(consumer_chain_id: &str) -> String {
+        format!("/logs/{consumer_chain_id}_ics_handshake.ron")
+    }
+
+    async fn load(provider_chain_id: &str, consumer_chain_id: &str) -> Result<Self> {
+        let path = Self::path(consumer_chain_id);
+        let s = match FileOptions::read_to_string(&path).await {
+            Ok(s) => s,
+            // first run
+            Err(_) => {
+                return Ok(Self {
+                    provider_chain_id: provider_chain_id.to_owned(),
+                    consumer_chain_id: consumer_chain_id.to_owned(),
+                    ..Default::default()
+                })
+            }
+        };
+        let state: Self = ron::from_str(&s).stack_err(|| format!("failed to parse {path}"))?;
+        if state.provider_chain_id != provider_chain_id {
+            return Err(Error::from(format!(
+                "{path} is for provider {}, not {provider_chain_id}",
+                state.provider_chain_id
+            )))
+        }
+        Ok(state)
+    }
+
+    async fn save(&self) -> Result<()> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).stack()?;
+        FileOptions::write_str(&Self::path(&self.consumer_chain_id), &s)
+            .await
+            .stack()
+    }
+
+    fn consumer_connection(&self) -> Result<&str> {
+        self.consumer_connection
+            .as_deref()
+            .stack_err(|| "no connection was found, the `connection` step needs to be run first")
+    }
+
+    fn provider_connection(&self) -> Result<&str> {
+        self.provider_connection
+            .as_deref()
+            .stack_err(|| "no connection was found, the `connection` step needs to be run first")
+    }
+
+    fn consumer_transfer_channel(&self) -> Result<&str> {
+        self.consumer_transfer_channel.as_deref().stack_err(|| {
+            "the consumer has not initialized a transfer channel, the `ccv-channel` step needs to \
+             be run first"
+        })
+    }
+
+    fn provider_transfer_channel(&self) -> Result<&str> {
+        self.provider_transfer_channel.as_deref().stack_err(|| {
+            "no provider transfer channel, the `transfer-try` step needs to be run first"
+        })
+    }
+}
+
+/// Picks the candidate with the `saved` ID, or else the first candidate
+fn pick<T>(candidates: Vec<T>, id: impl Fn(&T) -> &str, saved: &Option<String>) -> Option<T> {
+    let i = candidates
+        .iter()
+        .position(|candidate| Some(id(candidate)) == saved.as_deref())
+        .unwrap_or(0);
+    candidates.into_iter().nth(i)
+}
+
+/// Redetects the results and completed steps from the chains
+async fn detect(
+    state: &mut HandshakeState,
+    clients: &IcsClients,
+    provider: &ChainProfile,
+    consumer: &ChainProfile,
+) -> Result<()> {
+    let provider_grpc = provider.grpc_addr();
+    let consumer_grpc = consumer.grpc_addr();
+    state.completed.clear();
+    state.connection_progress = None;
+    state.ccv_confirm_pending = false;
+
+    let (open, pending): (Vec<_>, Vec<_>) =
+        query_client_connections(&consumer_grpc, &clients.consumer_client)
+            .await
+            .stack()?
+            .into_iter()
+            .filter(|connection| {
+                matches!(
+                    connection.state,
+                    ConnectionState::Init | ConnectionState::Open
+                ) && (connection.counterparty_client_id == clients.provider_client)
+            })
+            .partition(|connection| connection.state == ConnectionState::Open);
+    let connection = match pick(
+        open,
+        |connection| connection.connection_id.as_str(),
+        &state.consumer_connection,
+    ) {
+        Some(connection) => connection,
+        None => {
+            // an interrupted handshake, the provider end exists after `conn-try`
+            if let Some(connection) = pick(
+                pending,
+                |connection| connection.connection_id.as_str(),
+                &state.consumer_connection,
+            ) {
+                let provider_connection =
+                    query_client_connections(&provider_grpc, &clients.provider_client)
+                        .await
+                        .stack()?
+                        .into_iter()
+                        .find(|provider_connection| {
+                            (provider_connection.state == ConnectionState::TryOpen)
+                                && (provider_connection.counterparty_connection_id
+                                    == connection.connection_id)
+                        });
+                state.connection_progress = Some(if provider_connection.is_some() {
+                    ConnectionProgress::TryOpen
+                } else {
+                    ConnectionProgress::Init
+                });
+                state.consumer_connection = Some(connection.connection_id);
+                state.provider_connection =
+                    provider_connection.map(|connection| connection.connection_id);
+            }
+            return Ok(())
+        }
+    };
+    state.consumer_connection = Some(connection.connection_id.clone());
+    state.provider_connection = Some(connection.counterparty_connection_id.clone());
+    let provider_connection = query_connection(&provider_grpc, state.provider_connection()?)
+        .await
+        .stack()?;
+    if provider_connection.state != ConnectionState::Open {
+        state.connection_progress = Some(ConnectionProgress::Ack);
+        return Ok(())
+    }
+    state.completed.insert(Step::Connection);
+
+    let consumer_channels = query_connection_channels(&consumer_grpc, &connection.connection_id)
+        .await
+        .stack()?;
+    let ccv_channel = consumer_channels
+        .iter()
+        .find(|channel| (channel.port_id == "consumer") && (channel.state == ChannelState::Open));
+    if let Some(ccv_channel) = ccv_channel {
+        state.consumer_ccv_channel = Some(ccv_channel.channel_id.clone());
+        state.provider_ccv_channel = Some(ccv_channel.counterparty_channel_id.clone());
+        let provider_ccv_channel = query_channel(
+            &provider_grpc,
+            &ccv_channel.counterparty_port_id,
+            &ccv_channel.counterparty_channel_id,
+        )
+        .await
+        .stack()?;
+        if provider_ccv_channel.state == ChannelState::Open {
+            state.completed.insert(Step::CcvChannel);
+        } else {
+            state.ccv_confirm_pending = true;
+        }
+    }
+
+    let transfer_channels: Vec<ChannelInfo> = consumer_channels
+        .into_iter()
+        .filter(|channel| {
+            (channel.port_id == "transfer")
+                && matches!(channel.state, ChannelState::Init | ChannelState::Open)
+        })
+        .collect();
+    let consumer_transfer = match pick(
+        transfer_channels,
+        |channel| channel.channel_id.as_str(),
+        &state.consumer_transfer_channel,
+    ) {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    state.consumer_transfer_channel = Some(consumer_transfer.channel_id.clone());
+    if consumer_transfer.state == ChannelState::Open {
+        state.completed.insert(Step::TransferAck);
+    }
+
+    let provider_transfer = query_connection_channels(&provider_grpc, state.provider_connection()?)
+        .await
+        .stack()?
+        .into_iter()
+        .find(|channel| {
+            (channel.port_id == "transfer")
+                && (channel.counterparty_channel_id == consumer_transfer.channel_id)
+        });
+    if let Some(provider_transfer) = provider_transfer {
+        state.provider_transfer_channel = Some(provider_transfer.channel_id.clone());
+        state.completed.insert(Step::TransferTry);
+        if provider_transfer.state == ChannelState::Open {
+            state.completed.insert(Step::TransferConfirm);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the remaining transactions of an interrupted connection handshake
+async fn resume_connection(
+    state: &mut HandshakeState,
+    clients: &IcsClients,
+    provider_config: &ChainProfile,
+    consumer_config: &ChainProfile,
+) -> Result<()> {
+    let provider = provider_config.chain_id.clone();
+    let consumer = consumer_config.chain_id.clone();
+    let provider_client = &clients.provider_client;
+    let consumer_client = &clients.consumer_client;
+    while let Some(progress) = state.connection_progress {
+        let consumer_connection = state.consumer_connection().stack()?.to_owned();
+        info!("resuming the handshake of connection {consumer_connection} after {progress:?}");
+        let command = match progress {
+            ConnectionProgress::Init => format!(
+                "tx conn-try --dst-chain {provider} --src-chain {consumer} --dst-client \
+                 {provider_client} --src-client {consumer_client} --src-connection \
+                 {consumer_connection}"
+            ),
+            ConnectionProgress::TryOpen => {
+                let provider_connection = state.provider_connection().stack()?;
+                format!(
+                    "tx conn-ack --dst-chain {consumer} --src-chain {provider} --dst-client \
+                     {consumer_client} --src-client {provider_client} --dst-connection \
+                     {consumer_connection} --src-connection {provider_connection}"
+                )
+            }
+            ConnectionProgress::Ack => {
+                let provider_connection = state.provider_connection().stack()?;
+                format!(
+                    "tx conn-confirm --dst-chain {provider} --src-chain {consumer} --dst-client \
+                     {provider_client} --src-client {consumer_client} --dst-connection \
+                     {provider_connection} --src-connection {consumer_connection}"
+                )
+            }
+        };
+        sh_hermes([command]).await.stack()?;
+        detect(state, clients, provider_config, consumer_config)
+            .await
+            .stack()?;
+        if state.connection_progress == Some(progress) {
+            return Err(Error::from(format!(
+                "the handshake of connection {consumer_connection} did not progress past \
+                 {progress:?}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+async fn run_step(
+    step: Step,
+    state: &mut HandshakeState,
+    clients: &IcsClients,
+    provider_config: &ChainProfile,
+    consumer_config: &ChainProfile,
+) -> Result<()> {
+    let provider = state.provider_chain_id.clone();
+    let consumer = state.consumer_chain_id.clone();
+    match step {
+        Step::Connection => {
+            if state.connection_progress.is_some() {
+                resume_connection(state, clients, provider_config, consumer_config)
+                    .await
+                    .stack()?;
+            } else {
+                create_connection_pair(
+                    &consumer,
+                    &clients.consumer_client,
+                    &clients.provider_client,
+                )
+                .await
+                .stack()?;
+            }
+        }
+        Step::CcvChannel if state.ccv_confirm_pending => {
+            let provider_connection = state.provider_connection().stack()?;
+            let consumer_channel = state.consumer_ccv_channel.as_deref().stack()?;
+            let provider_channel = state.provider_ccv_channel.as_deref().stack()?;
+            info!("resuming the handshake of CCV channel {consumer_channel} after the ack");
+            sh_hermes([format!(
+                "tx chan-open-confirm --dst-chain {provider} --src-chain {consumer} \
+                 --dst-connection {provider_connection} --dst-port provider --src-port consumer \
+                 --dst-channel {provider_channel} --src-channel {consumer_channel}"
+            )])
+            .await
+            .stack()?;
+        }
+        Step::CcvChannel => {
+            create_channel_pair(
+                &consumer,
+                state.consumer_connection().stack()?,
+                "consumer",
+                "provider",
+                true,
+            )
+            .await
+            .stack()?;
+        }
+        Step::TransferTry => {
+            let provider_connection = state.provider_connection().stack()?;
+            let consumer_channel = state.consumer_transfer_channel().stack()?;
+            sh_hermes([format!(
+                "tx chan-open-try --dst-chain {provider} --src-chain {consumer} --dst-connection \
+                 {provider_connection} --dst-port transfer --src-port transfer --src-channel \
+                 {consumer_channel}"
+            )])
+            .await
+            .stack()?;
+        }
+        Step::TransferAck => {
+            let consumer_connection = state.consumer_connection().stack()?;
+            let consumer_channel = state.consumer_transfer_channel().stack()?;
+            let provider_channel = state.provider_transfer_channel().stack()?;
+            sh_hermes([format!(
+                "tx chan-open-ack --dst-chain {consumer} --src-chain {provider} --dst-connection \
+                 {consumer_connection} --dst-port transfer --src-port transfer --dst-channel \
+                 {consumer_channel} --src-channel {provider_channel}"
+            )])
+            .await
+            .stack()?;
+        }
+        Step::TransferConfirm => {
+            let provider_connection = state.provider_connection().stack()?;
+            let consumer_channel = state.consumer_transfer_channel().stack()?;
+            let provider_channel = state.provider_transfer_channel().stack()?;
+            sh_hermes([format!(
+                "tx chan-open-confirm --dst-chain {provider} --src-chain {consumer} \
+                 --dst-connection {provider_connection} --dst-port transfer --src-port transfer \
+                 --dst-channel {provider_channel} --src-channel {consumer_channel}"
+            )])
+            .await
+            .stack()?;
+        }
+    }
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let args = InitArgs::parse();
 
-    if let Some(ref s) = args.entry_name {
+    if let Some(ref s) = args.relayer_args.profile_args.args.entry_name {
         match s.as_str() {
             "hermes" => hermes_runner(&args).await,
             _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
@@ -48,10 +493,10 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn container_runner(args: &Args) -> Result<()> {
+async fn container_runner(args: &InitArgs) -> Result<()> {
     let logs_dir = "./tests/logs";
     let dockerfiles_dir = "./tests/dockerfiles";
-    let bin_entrypoint = &args.bin_name;
+    let bin_entrypoint = &args.relayer_args.profile_args.args.bin_name;
     let container_target = "x86_64-unknown-linux-gnu";
 
     // build internal runner with `--release`
@@ -64,24 +509,60 @@ async fn container_runner(args: &Args) -> Result<()> {
     .await
     .stack()?;
 
-    FileOptions::copy(
-        args.mnemonic_path
-            .as_deref()
-            .stack_err(|| "need --mnemonic-path")?,
-        "./tests/resources/tmp/mnemonic.txt",
-    )
-    .await
-    .stack()?;
+    // only keep the provider and the consumer we are connecting
+    let relayer_args = &args.relayer_args;
+    let network = relayer_args.network().await.stack()?;
+    let (provider, consumers) = network.ics_chains().stack()?;
+    let consumer = match relayer_args.profile_args.args.consumer_id {
+        Some(ref consumer_id) => consumers
+            .into_iter()
+            .find(|chain| &chain.chain_id == consumer_id)
+            .stack_err(|| format!("consumer {consumer_id} is not in the network"))?,
+        None if consumers.len() == 1 => consumers[0],
+        None => return Err(Error::from("need --consumer-id")),
+    };
+    let mut network = Network {
+        chains: vec![provider.clone(), consumer.clone()],
+    };
+    network.resolve_ics_clients().await.stack()?;
+    network.write_resolved().await.stack()?;
+
+    let mnemonic_path = relayer_args
+        .profile_args
+        .args
+        .mnemonic_path
+        .as_deref()
+        .stack_err(|| "need --mnemonic-path")?;
+    // make sure the relayer can pay for client updates before starting
+    let mnemonic = FileOptions::read_to_string(mnemonic_path).await.stack()?;
+    network
+        .check_wallets(&mnemonic, relayer_args)
+        .await
+        .stack()?;
+    FileOptions::copy(mnemonic_path, "./tests/resources/tmp/mnemonic.txt")
+        .await
+        .stack()?;
 
     let entrypoint = &format!("./target/{container_target}/release/{bin_entrypoint}");
 
+    let mut hermes_args = vec!["--entry-name".to_owned(), "hermes".to_owned()];
+    // pass on these args to the hermes runner
+    if let Some(step) = args.from_step {
+        hermes_args.push("--from-step".to_owned());
+        hermes_args.push(step.name());
+    }
+    if let Some(step) = args.only_step {
+        hermes_args.push("--only-step".to_owned());
+        hermes_args.push(step.name());
+    }
+
     let mut cn = ContainerNetwork::new(
         "test",
         vec![Container::new(
             "hermes",
             Dockerfile::contents(dockerfile_hermes("__tmp_hermes_config.toml")),
         )
-        .external_entrypoint(entrypoint, ["--entry-name", "hermes"])
+        .external_entrypoint(entrypoint, hermes_args)
         .await
         .stack()?],
         Some(dockerfiles_dir),
@@ -93,30 +574,12 @@ async fn container_runner(args: &Args) -> Result<()> {
     let uuid = cn.uuid_as_string();
     cn.add_common_entrypoint_args(["--uuid", &uuid]);
 
-    let onex_hermes = HermesChainConfig::new(
-        CONSUMER_CHAIN_ID,
-        CONSUMER_NODE,
-        "onomy",
-        true,
-        "abtc",
-        false,
-    );
-    // in case the ports are changed from their defaults
-    //onex_hermes.rpc_addr = format!("http://{CONSUMER_NODE}:36657");
-    //onex_hermes.grpc_addr = format!("http://{CONSUMER_NODE}:9292");
-    //onex_hermes.event_addr = format!("ws://{CONSUMER_NODE}:36657/websocket");
-
-    let mut onomy_hermes =
-        HermesChainConfig::new(ONOMY_CHAIN_ID, ONOMY_NODE, "onomy", false, "anom", false);
-    onomy_hermes.grpc_addr = format!("http://{ONOMY_NODE}:9191");
-
     // prepare hermes config
-    write_hermes_config(
-        &[onomy_hermes, onex_hermes],
-        &format!("{dockerfiles_dir}/dockerfile_resources"),
-    )
-    .await
-    .stack()?;
+    network
+        .hermes_config()
+        .write(&format!("{dockerfiles_dir}/dockerfile_resources"))
+        .await
+        .stack()?;
 
     cn.run_all(true).await.stack()?;
     cn.wait_with_timeout_all(true, TIMEOUT).await.stack()?;
@@ -124,7 +587,23 @@ async fn container_runner(args: &Args) -> Result<()> {
     Ok(())
 }
 
-async fn hermes_runner(_args: &Args) -> Result<()> {
+async fn hermes_runner(args: &InitArgs) -> Result<()> {
+    let network = Network::load_resolved().await.stack()?;
+    let (provider, consumers) = network.ics_chains().stack()?;
+    let consumer = consumers[0];
+    let clients = discover_ics_clients(
+        &provider.chain_id,
+        &provider.grpc_addr(),
+        &consumer.chain_id,
+        &consumer.grpc_addr(),
+    )
+    .await
+    .stack()?;
+    info!(
+        "provider {} has client {}, consumer {} has client {}",
+        provider.chain_id, clients.provider_client, consumer.chain_id, clients.consumer_client
+    );
+
     let mnemonic = FileOptions::read_to_string("/resources/tmp/mnemonic.txt")
         .await
         .stack()?;
@@ -134,97 +613,54 @@ async fn hermes_runner(_args: &Args) -> Result<()> {
         .await
         .stack()?;
 
-    sh_hermes([format!(
-        "keys add --chain {ONOMY_CHAIN_ID} --mnemonic-file /root/.hermes/dealer_mnemonic.txt"
-    )])
-    .await
-    .stack()?;
-    sh_hermes([format!(
-        "keys add --chain {CONSUMER_CHAIN_ID} --mnemonic-file /root/.hermes/dealer_mnemonic.txt"
-    )])
-    .await
-    .stack()?;
-
-    // NOTE: if failure occurs in the middle, you will need to comment out parts
-    // that have already succeeded
-    // a client is already created because of the ICS setup,
-    // do not run this unless you are creating another kind of client
-    //let client_pair = create_client_pair(a_chain, b_chain).await.stack()?;
-
-    // NOTE: comment out the last relaying part when initiating ics channels
-
-    // create one client and connection pair that will be used for IBC transfer and
-    // ICS communication
-
-    /*
-    // to get the client id
-    //cosmovisor run query provider list-consumer-chains
-    let provider_client = "07-tendermint-10";
-    // should always be this
-    let consumer_client = "07-tendermint-0";
-
-    let connection_pair = onomy_test_lib::hermes::create_connection_pair(
-        &CONSUMER_CHAIN_ID,
-        consumer_client,
-        provider_client,
-    )
-    .await
-    .stack()?;
-
-    onomy_test_lib::hermes::create_channel_pair(
-        &CONSUMER_CHAIN_ID,
-        &connection_pair.0,
-        "consumer",
-        "provider",
-        true,
-    )
-    .await
-    .stack()?;
-    */
-    /*
-    // these should always be the same
-    let provider = ONOMY_CHAIN_ID;
-    let consumer = CONSUMER_CHAIN_ID;
-    let consumer_channel = "channel-1";
-    let consumer_connection = "connection-0";
-    */
-
-    // after running the above, figure out which provider connection is needed, the
-    // result should have it or you could query the hermes binary interactively
-
-    /*
-    let provider_connection = "connection-15";
-    */
-    /*
-    sh_hermes([format!(
-        "tx chan-open-try --dst-chain {provider} --src-chain {consumer} --dst-connection \
-         {provider_connection} --dst-port transfer --src-port transfer --src-channel \
-         {consumer_channel}"
-    )])
-    .await
-    .stack()?;
-    */
-
-    /*
-    // get this from the above op
-    let provider_channel = "channel-10";
+    for chain in &network.chains {
+        sh_hermes([format!(
+            "keys add --chain {} --mnemonic-file /root/.hermes/dealer_mnemonic.txt",
+            chain.chain_id
+        )])
+        .await
+        .stack()?;
+    }
 
-    sh_hermes([format!(
-        "tx chan-open-ack --dst-chain {consumer} --src-chain {provider} --dst-connection \
-         {consumer_connection} --dst-port transfer --src-port transfer --dst-channel \
-         {consumer_channel} --src-channel {provider_channel}"
-    )])
-    .await
-    .stack()?;
+    let steps: Vec<Step> = if let Some(step) = args.only_step {
+        vec![step]
+    } else if let Some(from_step) = args.from_step {
+        STEPS
+            .into_iter()
+            .filter(|step| *step >= from_step)
+            .collect()
+    } else {
+        STEPS.to_vec()
+    };
 
-    sh_hermes([format!(
-        "tx chan-open-confirm --dst-chain {provider} --src-chain {consumer} --dst-connection \
-         {provider_connection} --dst-port transfer --src-port transfer --dst-channel \
-         {provider_channel} --src-channel {consumer_channel}"
-    )])
-    .await
-    .stack()?;
-    */
+    let mut state = HandshakeState::load(&provider.chain_id, &consumer.chain_id)
+        .await
+        .stack()?;
+    detect(&mut state, &clients, provider, consumer)
+        .await
+        .stack()?;
+    state.save().await.stack()?;
+    for step in steps {
+        if state.completed.contains(&step) {
+            info!("step {} has already completed", step.name());
+            continue
+        }
+        info!("running step {}", step.name());
+        run_step(step, &mut state, &clients, provider, consumer)
+            .await
+            .stack_err(|| format!("step {} failed", step.name()))?;
+        detect(&mut state, &clients, provider, consumer)
+            .await
+            .stack()?;
+        state.save().await.stack()?;
+        if !state.completed.contains(&step) {
+            return Err(Error::from(format!(
+                "step {} ran but the chains do not show it as completed",
+                step.name()
+            )))
+        }
+    }
+    info!("handshake state: {state:#?}");
 
     // then we need to relay
     let mut hermes_runner = hermes_start("/logs/hermes_ics_runner.log").await.stack()?;
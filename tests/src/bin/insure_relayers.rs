@@ -1,96 +1,97 @@
 //! Run this manually once a week to insure that ICS channels do not expire
 //!
 //! Check the outputs in the cosole and in ./tests/logs/hermes_ics_runner.log to
-//! make sure there are no errors or significant warnings
+//! make sure there are no errors or significant warnings, `hermes_log_report`
+//! can summarize the log
+//!
+//! The chains and clients to update are selected with `--network` from
+//! `--networks-path` (./tests/configs/networks.ron by default), the single
+//! chain overrides like `--grpc` are not supported since every chain is used
+//!
+//! With `--daemon` this runs indefinitely instead, relaying with Hermes and
+//! updating every client once `--refresh-fraction` of its trusting period has
+//! passed since its last update, retrying with backoff on failure. The status
+//! of every client is written to ./tests/logs/insure_relayers_heartbeat.json
+//! after every round.
 
+#[rustfmt::skip]
 /*
 e.x.
 
-cargo r --bin insure_relayers -- --mnemonic-path ./../testnet_dealer_mnemonic.txt
+cargo r --bin insure_relayers -- --mnemonic-path ./../testnet_dealer_mnemonic.txt --network testnet
+
+cargo r --bin insure_relayers -- --mnemonic-path ./../testnet_dealer_mnemonic.txt --network testnet --daemon
 
 // run this to be able to terminate or run `hermes` in the container
 cargo r --bin auto_exec_i -- --container-name hermes
 
 */
 
-use lazy_static::lazy_static;
-use log::warn;
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use common::{
+    ibc::query_client_status,
+    profiles::ChainProfile,
+    relayer_config::{Network, RelayerArgs},
+};
+use log::{info, warn};
 use onomy_test_lib::{
     dockerfiles::dockerfile_hermes,
-    hermes::{hermes_start, sh_hermes, write_hermes_config, HermesChainConfig},
-    onomy_std_init,
+    hermes::{hermes_start, sh_hermes},
     super_orchestrator::{
         docker::{Container, ContainerNetwork, Dockerfile},
         sh,
         stacked_errors::{Error, Result, StackableErr},
-        FileOptions,
+        std_init, FileOptions,
     },
-    Args, TIMEOUT,
+    TIMEOUT,
 };
+use serde::Serialize;
 use tokio::time::sleep;
 
-lazy_static! {
-    // A list of chains and their corresponding ICS clients that should be updated.
-    // The provider chain has a client for every consumer, and the consumer chains
-    // have one client with their provider.
-    static ref HERMES_CONFIGS: Vec<(HermesChainConfig, Vec<String>)> = vec![
-        // mainnet TODO
-
-        // testnet
-        {
-            let hostname = "34.145.158.212";
-            let mut config = HermesChainConfig::new(
-                "onomy-testnet-1",
-                hostname,
-                "onomy",
-                false,
-                "anom",
-                false,
-            );
-            config.grpc_addr = format!("http://{hostname}:9191");
-            (
-                config,
-                vec!["07-tendermint-10".to_owned()],
-            )
-        },
-        (
-            HermesChainConfig::new(
-                "onex-testnet-4",
-                "34.86.135.162",
-                "onomy",
-                true,
-                "abtc",
-                false,
-            ),
-            vec!["07-tendermint-0".to_owned()],
-        ),
-
-        // Note: in case you are running multiple nodes on the same machine and are using
-        // different port numbers from the default, you can change them like this
-        /*{
-            let hostname = "34.86.135.162";
-            let mut config = HermesChainConfig::new(
-                "onex-testnet-3",
-                hostname,
-                "onomy",
-                true,
-                "anom",
-                false,
-            );
-            config.rpc_addr = format!("http://{hostname}:36657");
-            config.grpc_addr = format!("http://{hostname}:9292");
-            config.event_addr = format!("ws://{hostname}:36657/websocket");
-
-            (config, vec!["07-tendermint-0".to_owned()])
-        },*/
-    ];
+/// For waiting on the daemon, which never finishes by itself
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+/// The longest time the daemon goes without rechecking the clients and writing
+/// the heartbeat
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const HEARTBEAT_PATH: &str = "/logs/insure_relayers_heartbeat.json";
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct InsureArgs {
+    #[command(flatten)]
+    relayer_args: RelayerArgs,
+    /// Run indefinitely, updating clients on a schedule
+    #[arg(long)]
+    daemon: bool,
+    /// In daemon mode, update a client after this fraction of its trusting
+    /// period has passed since its last update
+    #[arg(long, default_value_t = 0.25, value_parser = parse_fraction)]
+    refresh_fraction: f64,
+    /// In daemon mode, the retries of a failed client update before giving up
+    /// until the next round
+    #[arg(long, default_value_t = 6)]
+    max_retries: usize,
+}
+
+/// Parses a fraction in (0, 1]
+fn parse_fraction(s: &str) -> std::result::Result<f64, String> {
+    let fraction: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (fraction > 0.0) && (fraction <= 1.0) {
+        Ok(fraction)
+    } else {
+        Err(format!("{fraction} is not in (0, 1]"))
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
+    std_init()?;
+    let args = InsureArgs::parse();
 
-    if let Some(ref s) = args.entry_name {
+    if let Some(ref s) = args.relayer_args.profile_args.args.entry_name {
         match s.as_str() {
             "hermes" => hermes_runner(&args).await,
             _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
@@ -100,10 +101,11 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn container_runner(args: &Args) -> Result<()> {
+async fn container_runner(insure_args: &InsureArgs) -> Result<()> {
+    let args = &insure_args.relayer_args;
     let logs_dir = "./tests/logs";
     let dockerfiles_dir = "./tests/dockerfiles";
-    let bin_entrypoint = &args.bin_name;
+    let bin_entrypoint = &args.profile_args.args.bin_name;
     let container_target = "x86_64-unknown-linux-gnu";
 
     // build internal runner with `--release`
@@ -116,24 +118,42 @@ async fn container_runner(args: &Args) -> Result<()> {
     .await
     .stack()?;
 
-    FileOptions::copy(
-        args.mnemonic_path
-            .as_deref()
-            .stack_err(|| "need --mnemonic-path")?,
-        "./tests/resources/tmp/mnemonic.txt",
-    )
-    .await
-    .stack()?;
+    let mut network = args.network().await.stack()?;
+    network.resolve_ics_clients().await.stack()?;
+    network.write_resolved().await.stack()?;
+
+    let mnemonic_path = args
+        .profile_args
+        .args
+        .mnemonic_path
+        .as_deref()
+        .stack_err(|| "need --mnemonic-path")?;
+    // make sure the relayer can pay for client updates before starting
+    let mnemonic = FileOptions::read_to_string(mnemonic_path).await.stack()?;
+    network.check_wallets(&mnemonic, args).await.stack()?;
+    FileOptions::copy(mnemonic_path, "./tests/resources/tmp/mnemonic.txt")
+        .await
+        .stack()?;
 
     let entrypoint = &format!("./target/{container_target}/release/{bin_entrypoint}");
 
+    let mut hermes_args = vec!["--entry-name".to_owned(), "hermes".to_owned()];
+    // pass on these args to the hermes runner
+    if insure_args.daemon {
+        hermes_args.push("--daemon".to_owned());
+        hermes_args.push("--refresh-fraction".to_owned());
+        hermes_args.push(insure_args.refresh_fraction.to_string());
+        hermes_args.push("--max-retries".to_owned());
+        hermes_args.push(insure_args.max_retries.to_string());
+    }
+
     let mut cn = ContainerNetwork::new(
         "test",
         vec![Container::new(
             "hermes",
             Dockerfile::contents(dockerfile_hermes("__tmp_hermes_config.toml")),
         )
-        .external_entrypoint(entrypoint, ["--entry-name", "hermes"])
+        .external_entrypoint(entrypoint, hermes_args)
         .await
         .stack()?],
         Some(dockerfiles_dir),
@@ -146,23 +166,185 @@ async fn container_runner(args: &Args) -> Result<()> {
     cn.add_common_entrypoint_args(["--uuid", &uuid]);
 
     // prepare hermes config
-    write_hermes_config(
-        &HERMES_CONFIGS
-            .iter()
-            .map(|config_and_clients| config_and_clients.0.clone())
-            .collect::<Vec<HermesChainConfig>>(),
-        &format!("{dockerfiles_dir}/dockerfile_resources"),
-    )
-    .await
-    .stack()?;
+    network
+        .hermes_config()
+        .write(&format!("{dockerfiles_dir}/dockerfile_resources"))
+        .await
+        .stack()?;
 
     cn.run_all(true).await.stack()?;
-    cn.wait_with_timeout_all(true, TIMEOUT).await.stack()?;
+    let timeout = if insure_args.daemon {
+        DAEMON_TIMEOUT
+    } else {
+        TIMEOUT
+    };
+    cn.wait_with_timeout_all(true, timeout).await.stack()?;
     cn.terminate_all().await;
     Ok(())
 }
 
-async fn hermes_runner(_args: &Args) -> Result<()> {
+/// The daemon's view of a client, written to the heartbeat file
+#[derive(Debug, Clone, Serialize)]
+struct ClientHeartbeat {
+    host_chain_id: String,
+    client_id: String,
+    /// The latest height and consensus state time of the client, these also
+    /// change when another relayer updates it
+    height: Option<String>,
+    last_update: Option<DateTime<Utc>>,
+    /// The last time this daemon updated the client successfully
+    last_success: Option<DateTime<Utc>>,
+    next_update: DateTime<Utc>,
+    consecutive_failures: usize,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Heartbeat {
+    time: DateTime<Utc>,
+    clients: Vec<ClientHeartbeat>,
+}
+
+/// Runs `update client` until it succeeds or `max_retries` is exceeded,
+/// doubling the delay between attempts
+async fn update_client(chain_id: &str, client_id: &str, max_retries: usize) -> Result<()> {
+    let mut delay = Duration::from_secs(10);
+    let mut attempt = 0;
+    loop {
+        let res = sh_hermes([format!(
+            "update client --host-chain {chain_id} --client {client_id}"
+        )])
+        .await
+        .stack();
+        match res {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                attempt += 1;
+                if attempt > max_retries {
+                    return Err(e)
+                }
+                warn!(
+                    "updating client {client_id} on {chain_id} failed, retrying in {delay:?}: \
+                     {e:?}"
+                );
+                sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Updates the client if it is due, and records the result in `heartbeat`
+async fn refresh_client(
+    chain: &ChainProfile,
+    heartbeat: &mut ClientHeartbeat,
+    args: &InsureArgs,
+) -> Result<()> {
+    let chain_id = &chain.chain_id;
+    let client_id = heartbeat.client_id.clone();
+    let grpc = chain.grpc_addr();
+    let retry_at = Utc::now() + chrono::Duration::from_std(HEARTBEAT_INTERVAL).stack()?;
+
+    let status = match query_client_status(chain_id, &grpc, &client_id).await {
+        Ok(status) => status,
+        Err(e) => {
+            warn!("could not query client {client_id} on {chain_id}: {e:?}");
+            heartbeat.consecutive_failures += 1;
+            heartbeat.last_error = Some(format!("{e:?}"));
+            heartbeat.next_update = retry_at;
+            return Ok(())
+        }
+    };
+    let refresh_period =
+        chrono::Duration::from_std(status.trusting_period.mul_f64(args.refresh_fraction))
+            .stack()?;
+    heartbeat.height = Some(format!(
+        "{}-{}",
+        status.revision_number, status.revision_height
+    ));
+    heartbeat.last_update = Some(status.last_update);
+    heartbeat.next_update = status.last_update + refresh_period;
+    if Utc::now() < heartbeat.next_update {
+        return Ok(())
+    }
+
+    info!("updating client {client_id} on {chain_id}");
+    if let Err(e) = update_client(chain_id, &client_id, args.max_retries).await {
+        warn!("giving up on updating client {client_id} on {chain_id} until the next round");
+        heartbeat.consecutive_failures += 1;
+        heartbeat.last_error = Some(format!("{e:?}"));
+        heartbeat.next_update = retry_at;
+        return Ok(())
+    }
+    heartbeat.last_success = Some(Utc::now());
+    heartbeat.consecutive_failures = 0;
+    heartbeat.last_error = None;
+    match query_client_status(chain_id, &grpc, &client_id).await {
+        Ok(status) => {
+            heartbeat.height = Some(format!(
+                "{}-{}",
+                status.revision_number, status.revision_height
+            ));
+            heartbeat.last_update = Some(status.last_update);
+            heartbeat.next_update = status.last_update + refresh_period;
+        }
+        Err(e) => {
+            warn!("could not query client {client_id} on {chain_id} after updating: {e:?}");
+            heartbeat.next_update = retry_at;
+        }
+    }
+    Ok(())
+}
+
+async fn daemon(network: &Network, args: &InsureArgs) -> Result<()> {
+    let mut heartbeats: BTreeMap<(String, String), ClientHeartbeat> = BTreeMap::new();
+    loop {
+        for chain in &network.chains {
+            for client_id in &chain.clients {
+                let heartbeat = heartbeats
+                    .entry((chain.chain_id.clone(), client_id.clone()))
+                    .or_insert_with(|| ClientHeartbeat {
+                        host_chain_id: chain.chain_id.clone(),
+                        client_id: client_id.clone(),
+                        height: None,
+                        last_update: None,
+                        last_success: None,
+                        next_update: Utc::now(),
+                        consecutive_failures: 0,
+                        last_error: None,
+                    });
+                refresh_client(chain, heartbeat, args).await.stack()?;
+            }
+        }
+
+        let now = Utc::now();
+        let heartbeat = Heartbeat {
+            time: now,
+            clients: heartbeats.values().cloned().collect(),
+        };
+        FileOptions::write_str(
+            HEARTBEAT_PATH,
+            &serde_json::to_string_pretty(&heartbeat).stack()?,
+        )
+        .await
+        .stack()?;
+
+        // sleep until the next client is due, but keep the heartbeat fresh
+        let next_update = heartbeats
+            .values()
+            .map(|heartbeat| heartbeat.next_update)
+            .min()
+            .unwrap_or(now);
+        let delay = (next_update - now)
+            .to_std()
+            .unwrap_or_default()
+            .clamp(Duration::from_secs(1), HEARTBEAT_INTERVAL);
+        sleep(delay).await;
+    }
+}
+
+async fn hermes_runner(args: &InsureArgs) -> Result<()> {
+    let network = Network::load_resolved().await.stack()?;
     let mnemonic = FileOptions::read_to_string("/resources/tmp/mnemonic.txt")
         .await
         .stack()?;
@@ -173,22 +355,28 @@ async fn hermes_runner(_args: &Args) -> Result<()> {
         .stack()?;
 
     // add the chains
-    for (config, _) in HERMES_CONFIGS.iter() {
+    for chain in &network.chains {
         sh_hermes([format!(
             "keys add --chain {} --mnemonic-file /root/.hermes/dealer_mnemonic.txt",
-            config.chain_id
+            chain.chain_id
         )])
         .await
         .stack()?;
     }
 
+    if args.daemon {
+        // relay packets in the background for as long as the daemon runs
+        let _hermes_runner = hermes_start("/logs/hermes_ics_runner.log").await.stack()?;
+        return daemon(&network, args).await.stack()
+    }
+
     // update clients once, insures clients are updated even with no packets being
     // relayed
-    for (config, clients) in HERMES_CONFIGS.iter() {
-        for client in clients {
+    for chain in &network.chains {
+        for client in &chain.clients {
             let res = sh_hermes([format!(
                 "update client --host-chain {} --client {}",
-                config.chain_id, client
+                chain.chain_id, client
             )])
             .await
             .stack();
@@ -0,0 +1,352 @@
+//! Reports the packets waiting to be relayed on every channel of a network in
+//! `tests/configs/networks.ron`. The channels over the ICS connections (the
+//! "consumer"/"provider" channel and the transfer channel) are found
+//! automatically, other channels can be listed under `channels` of a chain.
+//!
+//! For each direction of each channel this reports the packets that have not
+//! been received by the destination, the packets whose acknowledgements have
+//! not been relayed back to the source, and the age of the oldest packet that
+//! is still committed on the source. The age is found from the transaction that
+//! sent the packet, or from the block events for packets sent from `EndBlock`
+//! like the VSC packets of ICS.
+//!
+//! This exits with an error if any channel is above the thresholds or the age
+//! of an unrelayed packet cannot be found, so that it can be run from a cron
+//! job.
+
+/*
+e.x.
+
+cargo r --bin packet_health -- --network testnet --max-pending 5 --max-age-minutes 30
+
+*/
+
+use std::collections::BTreeSet;
+
+use chrono::Utc;
+use clap::Parser;
+use common::{
+    ibc::{
+        format_duration, query_channel, query_client_connections, query_client_status,
+        query_connection, query_connection_channels, query_packet_commitments,
+        query_send_packet_time, query_unreceived_packets,
+    },
+    ics::discover_ics_clients,
+    profiles::ProfileArgs,
+    relayer_config::Network,
+    GREEN, RED, RESET,
+};
+use cosmos_sdk_proto::ibc::core::{
+    channel::v1::State as ChannelState, connection::v1::State as ConnectionState,
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct Args {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// Fail if more than this many packets are waiting to be received, or
+    /// waiting for their acknowledgements to be relayed, in any direction
+    #[arg(long, default_value_t = 10)]
+    pub max_pending: usize,
+    /// Fail if the oldest unrelayed packet of any direction is older than this
+    #[arg(long, default_value_t = 60)]
+    pub max_age_minutes: i64,
+}
+
+/// One end of a channel
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ChannelEnd {
+    chain_id: String,
+    port_id: String,
+    channel_id: String,
+}
+
+impl std::fmt::Display for ChannelEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `pad` so that the table widths apply
+        f.pad(&format!(
+            "{} {}/{}",
+            self.chain_id, self.port_id, self.channel_id
+        ))
+    }
+}
+
+/// Orders the ends so that each channel is only reported once
+fn channel_pair(a: ChannelEnd, b: ChannelEnd) -> (ChannelEnd, ChannelEnd) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn grpc_addr(network: &Network, chain_id: &str) -> Result<String> {
+    network
+        .chains
+        .iter()
+        .find(|chain| chain.chain_id == chain_id)
+        .map(|chain| chain.grpc_addr())
+        .stack_err(|| format!("chain {chain_id} is not in the network"))
+}
+
+fn rpc_addr(network: &Network, chain_id: &str) -> Result<String> {
+    network
+        .chains
+        .iter()
+        .find(|chain| chain.chain_id == chain_id)
+        .map(|chain| chain.rpc_addr())
+        .stack_err(|| format!("chain {chain_id} is not in the network"))
+}
+
+/// Finds the open channels over the connections between the provider and
+/// every consumer
+async fn ics_channels(network: &Network) -> Result<Vec<(ChannelEnd, ChannelEnd)>> {
+    let (provider, consumers) = network.ics_chains().stack()?;
+    let mut res = vec![];
+    for consumer in consumers {
+        let consumer_grpc = consumer.grpc_addr();
+        let clients = discover_ics_clients(
+            &provider.chain_id,
+            &provider.grpc_addr(),
+            &consumer.chain_id,
+            &consumer_grpc,
+        )
+        .await
+        .stack()?;
+        for connection in query_client_connections(&consumer_grpc, &clients.consumer_client)
+            .await
+            .stack()?
+        {
+            if (connection.state != ConnectionState::Open)
+                || (connection.counterparty_client_id != clients.provider_client)
+            {
+                continue
+            }
+            for channel in query_connection_channels(&consumer_grpc, &connection.connection_id)
+                .await
+                .stack()?
+            {
+                if channel.state != ChannelState::Open {
+                    continue
+                }
+                res.push(channel_pair(
+                    ChannelEnd {
+                        chain_id: consumer.chain_id.clone(),
+                        port_id: channel.port_id,
+                        channel_id: channel.channel_id,
+                    },
+                    ChannelEnd {
+                        chain_id: provider.chain_id.clone(),
+                        port_id: channel.counterparty_port_id,
+                        channel_id: channel.counterparty_channel_id,
+                    },
+                ));
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Finds the counterparty of a channel listed in the config, by following the
+/// channel to its connection and client
+async fn configured_channel(
+    network: &Network,
+    chain_id: &str,
+    port_id: &str,
+    channel_id: &str,
+) -> Result<(ChannelEnd, ChannelEnd)> {
+    let grpc = grpc_addr(network, chain_id).stack()?;
+    let channel = query_channel(&grpc, port_id, channel_id).await.stack()?;
+    let connection = query_connection(&grpc, &channel.connection_id)
+        .await
+        .stack()?;
+    let client = query_client_status(chain_id, &grpc, &connection.client_id)
+        .await
+        .stack()?;
+    // make sure we can query the other end
+    grpc_addr(network, &client.counterparty_chain_id).stack()?;
+    Ok(channel_pair(
+        ChannelEnd {
+            chain_id: chain_id.to_owned(),
+            port_id: port_id.to_owned(),
+            channel_id: channel_id.to_owned(),
+        },
+        ChannelEnd {
+            chain_id: client.counterparty_chain_id,
+            port_id: channel.counterparty_port_id,
+            channel_id: channel.counterparty_channel_id,
+        },
+    ))
+}
+
+/// The packets sent from `src` to `dst` that are still committed on `src`
+#[derive(Debug, Clone)]
+struct DirectionHealth {
+    /// Packets that `dst` has not received
+    pending_recv: Vec<u64>,
+    /// Packets that `dst` has received, but whose acknowledgements have not
+    /// been relayed back to `src`
+    pending_ack: Vec<u64>,
+    /// The oldest committed packet, and its age if its send could be found
+    oldest: Option<(u64, Option<chrono::Duration>)>,
+}
+
+async fn direction_health(
+    network: &Network,
+    src: &ChannelEnd,
+    dst: &ChannelEnd,
+) -> Result<DirectionHealth> {
+    let src_grpc = grpc_addr(network, &src.chain_id).stack()?;
+    let dst_grpc = grpc_addr(network, &dst.chain_id).stack()?;
+    let commitments = query_packet_commitments(&src_grpc, &src.port_id, &src.channel_id)
+        .await
+        .stack()?;
+    let pending_recv =
+        query_unreceived_packets(&dst_grpc, &dst.port_id, &dst.channel_id, &commitments)
+            .await
+            .stack()?;
+    let pending_ack = commitments
+        .iter()
+        .copied()
+        .filter(|sequence| pending_recv.binary_search(sequence).is_err())
+        .collect();
+    let oldest = match commitments.first() {
+        Some(sequence) => {
+            let src_rpc = rpc_addr(network, &src.chain_id).stack()?;
+            let sent = query_send_packet_time(
+                &src_grpc,
+                &src_rpc,
+                &src.port_id,
+                &src.channel_id,
+                *sequence,
+            )
+            .await
+            .stack()?;
+            Some((*sequence, sent.map(|sent| Utc::now() - sent)))
+        }
+        None => None,
+    };
+    Ok(DirectionHealth {
+        pending_recv,
+        pending_ack,
+        oldest,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = Args::parse();
+
+    let network = Network::from_args(&args.profile_args).await.stack()?;
+    let max_age = chrono::Duration::minutes(args.max_age_minutes);
+
+    let mut problems = vec![];
+    let mut pairs = BTreeSet::new();
+    if network.chains.iter().any(|chain| chain.ccv_provider) {
+        match ics_channels(&network).await {
+            Ok(ics_pairs) => pairs.extend(ics_pairs),
+            Err(e) => {
+                println!("{RED}could not find the ICS channels: {e:?}{RESET}");
+                problems.push("ICS channel discovery failed".to_owned());
+            }
+        }
+    }
+    for chain in &network.chains {
+        for (port_id, channel_id) in &chain.channels {
+            match configured_channel(&network, &chain.chain_id, port_id, channel_id).await {
+                Ok(pair) => {
+                    pairs.insert(pair);
+                }
+                Err(e) => {
+                    println!(
+                        "{RED}could not find the counterparty of {} {port_id}/{channel_id}: \
+                         {e:?}{RESET}",
+                        chain.chain_id
+                    );
+                    problems.push(format!(
+                        "{} {port_id}/{channel_id}: counterparty not found",
+                        chain.chain_id
+                    ));
+                }
+            }
+        }
+    }
+
+    println!(
+        "{:<42} {:<42} {:>8} {:>8} {:>10} {:>14}",
+        "source", "destination", "recv", "ack", "oldest", "oldest age"
+    );
+    for (a, b) in &pairs {
+        for (src, dst) in [(a, b), (b, a)] {
+            let health = match direction_health(&network, src, dst).await {
+                Ok(health) => health,
+                Err(e) => {
+                    println!("{RED}{:<42} {:<42} query failed: {e:?}{RESET}", src, dst);
+                    problems.push(format!("{src} -> {dst}: query failed"));
+                    continue
+                }
+            };
+            let mut direction_problems = vec![];
+            if health.pending_recv.len() > args.max_pending {
+                direction_problems.push(format!(
+                    "{src} -> {dst}: {} packets not received",
+                    health.pending_recv.len()
+                ));
+            }
+            if health.pending_ack.len() > args.max_pending {
+                direction_problems.push(format!(
+                    "{src} -> {dst}: {} acknowledgements not relayed",
+                    health.pending_ack.len()
+                ));
+            }
+            let (oldest, oldest_age) = match health.oldest {
+                Some((sequence, Some(age))) => {
+                    if age > max_age {
+                        direction_problems.push(format!(
+                            "{src} -> {dst}: packet {sequence} has been unrelayed for {}",
+                            format_duration(age)
+                        ));
+                    }
+                    (sequence.to_string(), format_duration(age))
+                }
+                Some((sequence, None)) => {
+                    direction_problems.push(format!(
+                        "{src} -> {dst}: the age of unrelayed packet {sequence} is unknown"
+                    ));
+                    (sequence.to_string(), "?".to_owned())
+                }
+                None => ("-".to_owned(), "-".to_owned()),
+            };
+            println!(
+                "{}{:<42} {:<42} {:>8} {:>8} {:>10} {:>14}{RESET}",
+                if direction_problems.is_empty() {
+                    GREEN
+                } else {
+                    RED
+                },
+                src,
+                dst,
+                health.pending_recv.len(),
+                health.pending_ack.len(),
+                oldest,
+                oldest_age,
+            );
+            problems.extend(direction_problems);
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "channels above thresholds:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
@@ -2,13 +2,28 @@
 //!
 //! You should download as csv and rename and move it according to `CSV_INPUT`.
 //! The output file can subsequently be used in `bulk_send.rs` or
-//! `contest_genesis.rs`
+//! `contest_genesis.rs`. Every row that is not included in the output is
+//! written with its reason to `rejections-output`, so that participants can be
+//! followed up with.
+//!
+//! Pass `--sybil` to additionally flag multiple addresses per discord user,
+//! near-identical usernames, and bursts of submissions. Flagged records are
+//! written to `sybil-review-output`, and with `--keep-earliest-per-user` only
+//! the earliest address of each user is retained.
+//!
+//! Timestamps are parsed with `--timestamp-format` in `--timezone`, and
+//! `--opens`/`--closes` (in the same format or RFC 3339) drop entries outside
+//! of the submission window.
 
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    time::Duration,
+};
 
 use clap::Parser;
 use common::{
-    contest::{RawRecord, Record},
+    contest::{RawRecord, Record, RejectReason, Rejection, TimestampFormat},
+    sybil::{self, SybilOptions},
     MODULE_ACCOUNTS,
 };
 use log::{info, warn};
@@ -24,6 +39,44 @@ struct Args {
     pub csv_input: String,
     #[arg(long, default_value_t = String::from("./tests/resources/contest_whitelist.ron"))]
     pub ron_output: String,
+    #[arg(
+        long,
+        default_value_t = String::from("./tests/resources/contest_whitelist_rejections.csv")
+    )]
+    pub rejections_output: String,
+    /// Run the sybil analysis pass
+    #[arg(long)]
+    pub sybil: bool,
+    #[arg(
+        long,
+        default_value_t = String::from("./tests/resources/contest_whitelist_sybil_review.csv")
+    )]
+    pub sybil_review_output: String,
+    /// With `--sybil`, retain only the earliest address of each discord user
+    #[arg(long)]
+    pub keep_earliest_per_user: bool,
+    /// Maximum edit distance for usernames to be considered near-identical, 0
+    /// disables the check
+    #[arg(long, default_value_t = 1)]
+    pub username_distance: usize,
+    /// Flag whenever this many submissions happen within `burst-window-secs`,
+    /// 0 disables the check
+    #[arg(long, default_value_t = 5)]
+    pub burst_count: usize,
+    #[arg(long, default_value_t = 10)]
+    pub burst_window_secs: u64,
+    /// The `chrono` format of the timestamps, the default is for Google Forms
+    #[arg(long, default_value_t = String::from("%m/%d/%Y %H:%M:%S"))]
+    pub timestamp_format: String,
+    /// The IANA timezone that the timestamps are local to
+    #[arg(long, default_value_t = String::from("UTC"))]
+    pub timezone: String,
+    /// Entries before this time are dropped
+    #[arg(long)]
+    pub opens: Option<String>,
+    /// Entries after this time are dropped
+    #[arg(long)]
+    pub closes: Option<String>,
 }
 
 #[tokio::main]
@@ -44,40 +97,132 @@ async fn main() -> Result<()> {
         .trim(csv::Trim::All)
         .from_reader(csv_file.as_bytes());
     let mut raw_records = vec![];
-    for result in rdr.deserialize() {
+    for (i, result) in rdr.deserialize().enumerate() {
         // Notice that we need to provide a type hint for automatic
         // deserialization.
         let record: RawRecord = result.stack()?;
         //println!("{:?}", record);
-        raw_records.push(record);
+        // the row as seen in a spreadsheet, the header is row 1
+        raw_records.push((i + 2, record));
     }
 
-    // so when duplicates are removed, we retain the earliest record
-    raw_records.sort_by(|lhs, rhs| lhs.timestamp.cmp(&rhs.timestamp));
-
-    let mut records: BTreeMap<String, Record> = BTreeMap::new();
-    for raw_record in &raw_records {
-        if let Some(record) = Record::from_raw_record(raw_record) {
-            match records.entry(record.addr.clone()) {
-                Entry::Vacant(v) => {
-                    v.insert(record);
-                }
-                Entry::Occupied(o) => {
-                    // avoid duplicates
-                    info!("duplicates:\n{:?}\n{:?}", o.get(), record);
+    let timestamp_format = TimestampFormat::new(&args.timestamp_format, &args.timezone).stack()?;
+    let opens = match args.opens {
+        Some(ref s) => Some(timestamp_format.parse(s).stack()?),
+        None => None,
+    };
+    let closes = match args.closes {
+        Some(ref s) => Some(timestamp_format.parse(s).stack()?),
+        None => None,
+    };
+
+    let mut rejections: Vec<(usize, &RawRecord, RejectReason)> = vec![];
+    let mut parsed: Vec<(usize, &RawRecord, Record)> = vec![];
+    for (row, raw_record) in &raw_records {
+        match Record::from_raw_record(raw_record, &timestamp_format) {
+            Ok(record) => {
+                if opens.map_or(false, |opens| record.timestamp < opens) {
+                    rejections.push((*row, raw_record, RejectReason::TooEarly));
+                } else if closes.map_or(false, |closes| record.timestamp > closes) {
+                    rejections.push((*row, raw_record, RejectReason::TooLate));
+                } else {
+                    parsed.push((*row, raw_record, record));
                 }
             }
+            Err(reason) => rejections.push((*row, raw_record, reason)),
+        }
+    }
+
+    // so when duplicates are removed, we retain the earliest record
+    parsed.sort_by(|lhs, rhs| (lhs.2.timestamp, lhs.0).cmp(&(rhs.2.timestamp, rhs.0)));
+
+    let mut records: BTreeMap<String, (usize, Record)> = BTreeMap::new();
+    for (row, raw_record, record) in parsed {
+        match records.entry(record.addr.clone()) {
+            Entry::Vacant(v) => {
+                v.insert((row, record));
+            }
+            Entry::Occupied(o) => {
+                // avoid duplicates
+                info!("duplicates:\n{:?}\n{:?}", o.get().1, record);
+                rejections.push((row, raw_record, RejectReason::Duplicate {
+                    winning_row: o.get().0,
+                }));
+            }
         }
     }
     // make sure there are no module accounts in there
     for module_account in MODULE_ACCOUNTS {
-        if records.contains_key(*module_account) {
+        if let Some((row, _)) = records.remove(*module_account) {
             warn!("a module account was in the set");
-            records.remove(*module_account).unwrap();
+            let raw_record = &raw_records
+                .iter()
+                .find(|(i, _)| *i == row)
+                .stack_err(|| format!("no raw record for row {row}"))?
+                .1;
+            rejections.push((row, raw_record, RejectReason::ModuleAccount));
+        }
+    }
+
+    if args.sybil {
+        let mut ordered: Vec<(usize, Record)> = records.values().cloned().collect();
+        ordered.sort_by(|lhs, rhs| (lhs.1.timestamp, lhs.0).cmp(&(rhs.1.timestamp, rhs.0)));
+        let report = sybil::analyze(&ordered, &SybilOptions {
+            keep_earliest_per_user: args.keep_earliest_per_user,
+            username_distance: args.username_distance,
+            burst_count: args.burst_count,
+            burst_window: Duration::from_secs(args.burst_window_secs),
+        })
+        .stack()?;
+        for (row, kept_row) in &report.removed {
+            let (_, record) = ordered
+                .iter()
+                .find(|(i, _)| i == row)
+                .stack_err(|| format!("sybil analysis removed unknown row {row}"))?;
+            records
+                .remove(&record.addr)
+                .stack_err(|| format!("row {row} was already removed"))?;
+            let raw_record = &raw_records
+                .iter()
+                .find(|(i, _)| i == row)
+                .stack_err(|| format!("no raw record for row {row}"))?
+                .1;
+            rejections.push((*row, raw_record, RejectReason::SybilPolicy {
+                kept_row: *kept_row,
+            }));
+        }
+
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        let review_rows = report.review_rows(&ordered);
+        for review_row in &review_rows {
+            wtr.serialize(review_row).stack()?;
         }
+        let review_s = String::from_utf8(wtr.into_inner().stack()?).stack()?;
+        FileOptions::write_str(&args.sybil_review_output, &review_s)
+            .await
+            .stack()?;
+        info!(
+            "{} records were flagged for review, {} were removed by policy",
+            review_rows.len(),
+            report.removed.len()
+        );
+    }
+
+    rejections.sort_by_key(|(row, ..)| *row);
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    for (row, raw_record, reason) in &rejections {
+        wtr.serialize(Rejection::new(*row, raw_record, reason))
+            .stack()?;
     }
+    let rejections_s = String::from_utf8(wtr.into_inner().stack()?).stack()?;
+    FileOptions::write_str(args.rejections_output, &rejections_s)
+        .await
+        .stack()?;
+    info!("{} rows were rejected", rejections.len());
+    drop(rejections);
+
     drop(raw_records);
-    let records: Vec<Record> = records.values().cloned().collect();
+    let records: Vec<Record> = records.into_values().map(|(_, record)| record).collect();
     dbg!(records.len());
 
     let records_s = ron::to_string(&records).stack()?;
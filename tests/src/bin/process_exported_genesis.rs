@@ -13,17 +13,16 @@ cargo r --bin process_accounts --release -- --partial-genesis-without-accounts-p
 
 */
 
-use std::collections::{btree_map::Entry, BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 
 use clap::Parser;
-use common::MODULE_ACCOUNTS;
+use common::{genesis::delegated_amounts, MODULE_ACCOUNTS};
 use onomy_test_lib::super_orchestrator::{
     stacked_errors::{Result, StackableErr},
-    stacked_get, stacked_get_mut, std_init, FileOptions,
+    stacked_get_mut, std_init, FileOptions,
 };
 use serde::ser::Serialize;
 use serde_json::{json, ser::PrettyFormatter, Serializer, Value};
-use u64_array_bigints::U256;
 
 #[derive(Parser, Debug, Clone)]
 #[command(about)]
@@ -57,72 +56,14 @@ async fn main() -> Result<()> {
     let exported: Value = serde_json::from_str(&exported_genesis).stack()?;
     let mut genesis: Value = serde_json::from_str(&partial_genesis_without_accounts).stack()?;
 
-    let validators_value: &[Value] = stacked_get!(exported["app_state"]["staking"]["validators"])
-        .as_array()
-        .stack()?;
-
-    struct Total {
-        shares: U256,
-        tokens: U256,
-    }
-    let mut validators: BTreeMap<String, Total> = BTreeMap::new();
-    for validator in validators_value {
-        let shares = &validator["delegator_shares"];
-        let shares = shares.as_str().unwrap();
-        // the shares can be fractional, truncate at the decimal point
-        let i = shares.find('.').unwrap();
-        let shares = &shares[..i];
-        let shares = U256::from_dec_or_hex_str(shares).unwrap();
-        let tokens = U256::from_dec_or_hex_str(validator["tokens"].as_str().unwrap()).unwrap();
-        validators.insert(
-            validator["operator_address"].as_str().unwrap().to_owned(),
-            Total { shares, tokens },
-        );
-    }
-
     // use only bonded amounts
-    let delegations: &[Value] = stacked_get!(exported["app_state"]["staking"]["delegations"])
-        .as_array()
-        .stack()?;
-
-    let mut allocations = BTreeMap::<String, u128>::new();
-    for delegation in delegations {
-        let address = stacked_get!(delegation["delegator_address"]);
-        let address = address.as_str().unwrap();
-        if module_accounts.contains(address) {
+    let mut allocations = delegated_amounts(&exported).stack()?;
+    for address in allocations.keys() {
+        if module_accounts.contains(address.as_str()) {
             // there shouldn't be any modules delegating to anyone
             panic!();
             //continue
         }
-        let shares = stacked_get!(delegation["shares"]);
-        let shares = shares.as_str().unwrap();
-        // the shares can be fractional, truncate at the decimal point
-        let i = shares.find('.').unwrap();
-        let shares = &shares[..i];
-        let shares = U256::from_dec_or_hex_str(shares).unwrap();
-
-        let total = validators
-            .get(
-                stacked_get!(delegation["validator_address"])
-                    .as_str()
-                    .stack()?,
-            )
-            .unwrap();
-
-        // delegated tokens = (shares * total_tokens) / total_shares
-        let tmp = shares.checked_mul(total.tokens).unwrap();
-        let tmp = tmp.divide(total.shares).unwrap().0;
-        let tmp = tmp.try_resize_to_u128().unwrap();
-
-        match allocations.entry(address.to_owned()) {
-            Entry::Vacant(v) => {
-                v.insert(tmp);
-            }
-            Entry::Occupied(mut o) => {
-                // if multiple delegations from same address, add them up
-                *o.get_mut() = o.get().checked_add(tmp).unwrap();
-            }
-        }
     }
 
     let mut total_supply: u128 = allocations.values().sum();
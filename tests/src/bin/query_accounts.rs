@@ -1,52 +1,84 @@
-//! temporary for querying accounts
-
-use common::container_runner;
-use onomy_test_lib::{
-    cosmovisor::{sh_cosmovisor, sh_cosmovisor_no_debug},
-    dockerfiles::dockerfile_onexd,
-    onomy_std_init,
-    super_orchestrator::{
-        stacked_errors::{Error, Result, StackableErr},
-        FileOptions,
-    },
-    yaml_str_to_json_value, Args,
+//! Exports every account of `--chain` of `--network` in
+//! `tests/configs/networks.ron` over gRPC, with the account types decoded and
+//! the bank balances joined in. `--grpc` overrides the address of the chain.
+//!
+//! The accounts are paginated through at a single height, which is the latest
+//! height unless `--height` is given for a historical query (the node must not
+//! have pruned it). The export is written with one row per address to
+//! `<output-dir>/<chain-id>_accounts_<height>.csv` and to a `.json` of the same
+//! name, the JSON can be loaded back with `AccountExport::load`.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin query_accounts -- --network devnet
+
+cargo r --bin query_accounts -- --network mainnet --chain onomy --height 1000000
+
+*/
+
+use clap::Parser;
+use common::{accounts::AccountExport, profiles::ProfileArgs};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Result, StackableErr},
+    std_init, FileOptions,
 };
 
-const NODE: &str = "http://35.239.163.97:26657";
-const CHAIN_ID: &str = "onex-devnet-1";
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct ExportArgs {
+    #[command(flatten)]
+    profile_args: ProfileArgs,
+    /// The chain of `--network` to export
+    #[arg(long, default_value_t = String::from("onex"))]
+    chain: String,
+    /// Export the state at this height instead of the latest
+    #[arg(long)]
+    height: Option<u64>,
+    #[arg(long, default_value_t = String::from("./tests/logs"))]
+    output_dir: String,
+    /// The maximum number of balance queries in flight
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = onomy_std_init()?;
-
-    if let Some(ref s) = args.entry_name {
-        match s.as_str() {
-            "onexd" => onexd_runner(&args).await,
-            _ => Err(Error::from(format!("entry_name \"{s}\" is not recognized"))),
-        }
-    } else {
-        container_runner(&args, &[("onexd", &dockerfile_onexd())])
-            .await
-            .stack()
-    }
-}
-
-async fn onexd_runner(_args: &Args) -> Result<()> {
-    //let daemon_home = args.daemon_home.as_ref().stack()?;
+    std_init()?;
+    let args = ExportArgs::parse();
 
-    sh_cosmovisor(["config node", NODE]).await.stack()?;
-    sh_cosmovisor(["config chain-id", CHAIN_ID]).await.stack()?;
-
-    let accounts = sh_cosmovisor_no_debug(["query auth accounts --limit 10000"])
+    let chain = args.profile_args.resolve(&args.chain).await.stack()?;
+    let export = AccountExport::query(&chain.grpc, &chain.chain_id, args.height, args.concurrency)
         .await
         .stack()?;
 
-    FileOptions::write_str(
-        "/resources/query_accounts.json",
-        &yaml_str_to_json_value(&accounts).stack()?.to_string(),
-    )
-    .await
-    .stack()?;
+    let base_path = format!(
+        "{}/{}_accounts_{}",
+        args.output_dir, export.chain_id, export.height
+    );
+    FileOptions::write_str(&format!("{base_path}.csv"), &export.to_csv().stack()?)
+        .await
+        .stack()?;
+    FileOptions::write_str(&format!("{base_path}.json"), &export.to_json().stack()?)
+        .await
+        .stack()?;
 
+    println!(
+        "exported {} accounts of {} at height {} to {base_path}.csv and .json",
+        export.accounts.len(),
+        export.chain_id,
+        export.height
+    );
+    let mut kinds = std::collections::BTreeMap::<String, usize>::new();
+    for account in &export.accounts {
+        *kinds.entry(account.kind.to_string()).or_default() += 1;
+    }
+    for (kind, count) in kinds {
+        println!("  {kind:<26} {count:>8}");
+    }
+    for (denom, amount) in export.total_balances().stack()? {
+        println!("  total {denom:<68} {amount:>40}");
+    }
     Ok(())
 }
@@ -0,0 +1,167 @@
+//! Starts a container with the daemon of `--chain` configured for its node, use
+//! auto_exec_i to get into the container and issue `cosmovisor` commands. The
+//! container stays open until `TIMEOUT`.
+//!
+//! The chain is resolved from `--network` in `tests/configs/networks.ron`,
+//! `--node`, `--chain-id`, and `--fee-denom` override it. If `--mnemonic-path`
+//! is given the key is recovered as "validator" in the test keyring. New
+//! consumer daemons need a profile and an entry in `ResolvedChain::dockerfile`.
+//!
+//! With `--recipe`, the named queries of a recipe file (see
+//! `tests/configs/recipes/`) are run instead and the container exits. Each
+//! result is saved as JSON to `./tests/logs/<recipe>/<name>.json` for diffing
+//! and archiving, where `<recipe>` is the file stem of the recipe.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin query_chain -- --network testnet --chain onomy --mnemonic-path ./../testnet_dealer_mnemonic.txt
+
+cargo r --bin query_chain -- --network testnet --chain onex
+
+cargo r --bin query_chain -- --network mainnet --chain onomy
+
+cargo r --bin query_chain -- --network testnet --chain onex --recipe ./tests/configs/recipes/onex.ron
+
+// in another terminal, the container is named after the daemon
+cargo r --bin auto_exec_i -- --container-name onomyd
+
+*/
+
+use clap::Parser;
+use common::{
+    container_runner,
+    profiles::{ProfileArgs, ResolvedChain},
+    recipe::Recipe,
+};
+use log::info;
+use onomy_test_lib::{
+    cosmovisor::sh_cosmovisor,
+    super_orchestrator::{
+        stacked_errors::{Error, Result, StackableErr},
+        std_init, Command, FileOptions,
+    },
+    Args, TIMEOUT,
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+/// Where the container runner places the `QueryPlan`, the chain itself is
+/// placed with `ResolvedChain::write_resolved`
+const QUERY_PLAN_PATH: &str = "./tests/resources/tmp/query_chain.ron";
+/// `QUERY_PLAN_PATH` as seen from inside the container
+const CONTAINER_QUERY_PLAN_PATH: &str = "/resources/tmp/query_chain.ron";
+const MNEMONIC_PATH: &str = "./tests/resources/tmp/mnemonic.txt";
+const CONTAINER_MNEMONIC_PATH: &str = "/resources/tmp/mnemonic.txt";
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct QueryArgs {
+    #[command(flatten)]
+    profile_args: ProfileArgs,
+    /// The chain of `--network` to query, e.x. "onomy" or "onex"
+    #[arg(long, default_value_t = String::from("onomy"))]
+    chain: String,
+    /// Run the queries of this recipe and exit
+    #[arg(long)]
+    recipe: Option<String>,
+}
+
+/// What the container should do, written by the container runner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueryPlan {
+    /// The name of the resolved chain, for `ResolvedChain::load_resolved`
+    chain: String,
+    /// The recipe name and recipe
+    recipe: Option<(String, Recipe)>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = QueryArgs::parse();
+
+    if let Some(ref s) = args.profile_args.args.entry_name {
+        let plan: QueryPlan = ron::from_str(
+            &FileOptions::read_to_string(CONTAINER_QUERY_PLAN_PATH)
+                .await
+                .stack()?,
+        )
+        .stack()?;
+        let chain = ResolvedChain::load_resolved(&plan.chain).await.stack()?;
+        if s != &chain.daemon {
+            return Err(Error::from(format!("entry_name \"{s}\" is not recognized")))
+        }
+        chain_runner(&args.profile_args.args, &chain, &plan).await
+    } else {
+        let chain = args.profile_args.resolve(&args.chain).await.stack()?;
+        info!("querying {chain:#?}");
+        let recipe = match args.recipe {
+            Some(ref recipe_path) => Some((
+                Recipe::name(recipe_path).stack()?,
+                Recipe::load(recipe_path).await.stack()?,
+            )),
+            None => None,
+        };
+        chain.write_resolved().await.stack()?;
+        let plan = QueryPlan {
+            chain: chain.name.clone(),
+            recipe,
+        };
+        FileOptions::write_str(QUERY_PLAN_PATH, &ron::to_string(&plan).stack()?)
+            .await
+            .stack()?;
+
+        // an empty mnemonic means that no key is recovered
+        let mnemonic = match args.profile_args.args.mnemonic_path {
+            Some(ref mnemonic_path) => FileOptions::read_to_string(mnemonic_path).await.stack()?,
+            None => String::new(),
+        };
+        FileOptions::write_str(MNEMONIC_PATH, &mnemonic)
+            .await
+            .stack()?;
+
+        container_runner(&args.profile_args.args, &[(
+            &chain.daemon,
+            &chain.dockerfile().stack()?,
+        )])
+        .await
+        .stack()
+    }
+}
+
+async fn chain_runner(args: &Args, chain: &ResolvedChain, plan: &QueryPlan) -> Result<()> {
+    let daemon_home = args.daemon_home.as_ref().stack()?;
+
+    sh_cosmovisor(["config node", &chain.node]).await.stack()?;
+    sh_cosmovisor(["config chain-id", &chain.chain_id])
+        .await
+        .stack()?;
+    sh_cosmovisor(["config keyring-backend test"])
+        .await
+        .stack()?;
+
+    let mnemonic = FileOptions::read_to_string(CONTAINER_MNEMONIC_PATH)
+        .await
+        .stack()?;
+    if !mnemonic.trim().is_empty() {
+        Command::new(format!(
+            "{daemon_home}/cosmovisor/current/bin/{} keys add validator --recover",
+            chain.daemon
+        ))
+        .run_with_input_to_completion(mnemonic.as_bytes())
+        .await
+        .stack()?
+        .assert_success()
+        .stack()?;
+    }
+
+    if let Some((ref name, ref recipe)) = plan.recipe {
+        recipe.run(chain, &format!("/logs/{name}")).await.stack()
+    } else {
+        sh_cosmovisor(["query block"]).await.stack()?;
+        sleep(TIMEOUT).await;
+        Ok(())
+    }
+}
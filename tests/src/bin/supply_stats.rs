@@ -0,0 +1,388 @@
+//! Reports how the supply of a chain is distributed: the total and
+//! circulating supply per denom, the amount locked in vesting at `--at`, the
+//! module account holdings, the top holders, the concentration of stake, and a
+//! histogram of holdings
+//!
+//! The accounts come from `--genesis-path` (e.x. an exported genesis), from
+//! `--export-path` (a JSON export of `query_accounts`), or else from the live
+//! `--chain` of `--network` in `tests/configs/networks.ron`. Stake comes from
+//! the staking state of the genesis or of the live chain, an account export
+//! has no staking state and the stake statistics are skipped for it.
+//!
+//! The total supply is the sum of all balances. The circulating supply
+//! excludes the coins of module accounts other than the staking pools (staked
+//! coins belong to their delegators) and the coins still vesting at `--at`.
+//! Holdings are balances plus the tokens delegated if `--denom` is the staking
+//! denom, module accounts are excluded from the top holders and the histogram.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin supply_stats -- --network mainnet --chain onomy --top 50
+
+cargo r --bin supply_stats -- --genesis-path ./../../../Downloads/mainnet-snapshot-for-onex.json --denom anom
+
+cargo r --bin supply_stats -- --export-path ./tests/logs/onex-testnet-3_accounts_100.json --denom aonex --at 2024-09-01T00:00:00Z
+
+*/
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use common::{
+    accounts::{saturating_sub_coins, Account, AccountExport, AccountKind, Coins},
+    genesis::{delegated_amounts, genesis_validators},
+    profiles::ProfileArgs,
+    validators::{query_bond_denom, query_delegated_amounts, query_validators},
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    stacked_get, std_init, FileOptions,
+};
+use serde_json::Value;
+
+/// The module accounts that hold staked coins on behalf of delegators
+const STAKING_POOLS: &[&str] = &["bonded_tokens_pool", "not_bonded_tokens_pool"];
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct SupplyArgs {
+    // `--genesis-path` selects a genesis file to use the accounts and staking
+    // state of
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// The chain of `--network` to query, if no file is given
+    #[arg(long, default_value_t = String::from("onomy"))]
+    pub chain: String,
+    /// Use the accounts of this `query_accounts` JSON export
+    #[arg(long)]
+    pub export_path: Option<String>,
+    /// The denom of the holder statistics, defaults to the staking denom
+    #[arg(long)]
+    pub denom: Option<String>,
+    /// The number of decimals of `--denom`, for the histogram
+    #[arg(long, default_value_t = 18)]
+    pub decimals: u32,
+    /// The time to compute the vesting at, in RFC 3339 e.x.
+    /// "2024-09-01T00:00:00Z", defaults to now
+    #[arg(long)]
+    pub at: Option<String>,
+    /// The number of top holders to list
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+}
+
+/// Bonded validator tokens and the tokens that each delegator has delegated
+struct Stake {
+    bond_denom: String,
+    validators: Vec<(String, u128)>,
+    delegations: BTreeMap<String, u128>,
+}
+
+struct Holder<'a> {
+    address: &'a str,
+    balance: u128,
+    delegated: u128,
+}
+
+impl Holder<'_> {
+    fn total(&self) -> u128 {
+        self.balance.saturating_add(self.delegated)
+    }
+}
+
+/// The Gini coefficient of `amounts`, 0 is perfect equality and 1 is one
+/// holder with everything
+fn gini(amounts: &[u128]) -> f64 {
+    let mut amounts: Vec<f64> = amounts.iter().map(|amount| *amount as f64).collect();
+    amounts.sort_by(|a, b| a.total_cmp(b));
+    let n = amounts.len() as f64;
+    let sum: f64 = amounts.iter().sum();
+    if sum == 0.0 {
+        return 0.0
+    }
+    let weighted: f64 = amounts
+        .iter()
+        .enumerate()
+        .map(|(i, amount)| ((i + 1) as f64) * amount)
+        .sum();
+    (2.0 * weighted) / (n * sum) - (n + 1.0) / n
+}
+
+/// The minimum number of validators that together have more than a third of
+/// the bonded tokens, which is enough to halt the chain
+fn nakamoto_coefficient(tokens: &[u128]) -> usize {
+    let mut tokens = tokens.to_vec();
+    tokens.sort_unstable_by(|a, b| b.cmp(a));
+    let total: u128 = tokens.iter().sum();
+    let mut cumulative = 0u128;
+    for (i, amount) in tokens.iter().enumerate() {
+        cumulative += amount;
+        if cumulative * 3 > total {
+            return i + 1
+        }
+    }
+    tokens.len()
+}
+
+fn is_staking_pool(account: &Account) -> bool {
+    account
+        .module_name
+        .as_deref()
+        .map_or(false, |name| STAKING_POOLS.contains(&name))
+}
+
+fn percent(amount: u128, total: u128) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (amount as f64) * 100.0 / (total as f64)
+    }
+}
+
+fn print_supply(export: &AccountExport, time: DateTime<Utc>) -> Result<()> {
+    let total = export.total_balances().stack()?;
+    let mut modules = Coins::new();
+    let mut vesting = Coins::new();
+    for account in &export.accounts {
+        if (account.kind == AccountKind::Module) && !is_staking_pool(account) {
+            for (denom, amount) in &account.balances {
+                *modules.entry(denom.clone()).or_default() += amount;
+            }
+        }
+        for (denom, amount) in account.vesting_coins(time.timestamp()) {
+            *vesting.entry(denom).or_default() += amount;
+        }
+    }
+    let circulating = saturating_sub_coins(&saturating_sub_coins(&total, &modules), &vesting);
+
+    println!("supply at {}:", time.to_rfc3339());
+    println!(
+        "  {:<68} {:>40} {:>40} {:>40} {:>40}",
+        "denom", "total", "circulating", "vesting", "modules"
+    );
+    for (denom, amount) in &total {
+        let get = |coins: &Coins| coins.get(denom).copied().unwrap_or_default();
+        println!(
+            "  {denom:<68} {amount:>40} {:>40} {:>40} {:>40}",
+            get(&circulating),
+            get(&vesting),
+            get(&modules)
+        );
+    }
+    Ok(())
+}
+
+fn print_modules(export: &AccountExport, denom: &str, supply: u128) {
+    let mut modules: Vec<&Account> = export
+        .accounts
+        .iter()
+        .filter(|account| account.kind == AccountKind::Module)
+        .collect();
+    let balance = |account: &Account| account.balances.get(denom).copied().unwrap_or_default();
+    modules.sort_by_key(|account| std::cmp::Reverse(balance(account)));
+    println!("module accounts holding {denom}:");
+    for account in modules {
+        if balance(account) == 0 {
+            continue
+        }
+        println!(
+            "  {:<28} {:<52} {:>40} {:>7.3}%",
+            account.module_name.as_deref().unwrap_or_default(),
+            account.address,
+            balance(account),
+            percent(balance(account), supply)
+        );
+    }
+}
+
+fn print_holders(holders: &[Holder], top: usize, supply: u128) {
+    println!("top {top} holders:");
+    println!(
+        "  {:>4} {:<52} {:>40} {:>40} {:>40} {:>8}",
+        "rank", "address", "balance", "delegated", "total", "supply"
+    );
+    for (i, holder) in holders.iter().take(top).enumerate() {
+        println!(
+            "  {:>4} {:<52} {:>40} {:>40} {:>40} {:>7.3}%",
+            i + 1,
+            holder.address,
+            holder.balance,
+            holder.delegated,
+            holder.total(),
+            percent(holder.total(), supply)
+        );
+    }
+}
+
+/// Buckets the holdings by powers of ten of whole tokens
+fn print_histogram(holders: &[Holder], decimals: u32) {
+    let unit = 10u128.saturating_pow(decimals);
+    // bucket `i` holds totals in [10^(i - 1), 10^i) whole tokens, bucket 0 is
+    // below one token
+    let mut buckets = BTreeMap::<u32, (usize, u128)>::new();
+    for holder in holders {
+        let whole = holder.total() / unit;
+        let bucket = if whole == 0 { 0 } else { whole.ilog10() + 1 };
+        let entry = buckets.entry(bucket).or_default();
+        entry.0 += 1;
+        entry.1 = entry.1.saturating_add(holder.total());
+    }
+    println!("histogram of holdings in whole tokens:");
+    println!("  {:>24} {:>10} {:>40}", "range", "holders", "total");
+    for (bucket, (count, total)) in buckets {
+        let range = if bucket == 0 {
+            "< 1".to_owned()
+        } else {
+            format!(
+                "{} - {}",
+                10u128.pow(bucket - 1),
+                10u128.saturating_pow(bucket)
+            )
+        };
+        println!("  {range:>24} {count:>10} {total:>40}");
+    }
+}
+
+fn print_stake(stake: &Stake) {
+    let tokens: Vec<u128> = stake.validators.iter().map(|(_, tokens)| *tokens).collect();
+    let delegations: Vec<u128> = stake.delegations.values().copied().collect();
+    println!(
+        "stake: {} bonded validators with {} tokens, {} delegators",
+        tokens.len(),
+        tokens.iter().sum::<u128>(),
+        delegations.len()
+    );
+    println!("  Gini coefficient of the validators: {:.4}", gini(&tokens));
+    println!(
+        "  Gini coefficient of the delegators: {:.4}",
+        gini(&delegations)
+    );
+    println!("  Nakamoto coefficient: {}", nakamoto_coefficient(&tokens));
+    let mut validators = stake.validators.clone();
+    validators.sort_by(|a, b| b.1.cmp(&a.1));
+    let total: u128 = tokens.iter().sum();
+    let mut cumulative = 0;
+    for (moniker, tokens) in validators.iter().take(nakamoto_coefficient(&tokens)) {
+        cumulative += tokens;
+        println!(
+            "    {:<24} {:>40} {:>7.3}% (cumulative {:.3}%)",
+            moniker.chars().take(24).collect::<String>(),
+            tokens,
+            percent(*tokens, total),
+            percent(cumulative, total)
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = SupplyArgs::parse();
+
+    let time = match args.at {
+        Some(ref at) => DateTime::parse_from_rfc3339(at)
+            .stack_err(|| format!("invalid --at {at}"))?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+
+    let (export, stake) = if let Some(ref genesis_path) = args.profile_args.args.genesis_path {
+        let genesis: Value =
+            serde_json::from_str(&FileOptions::read_to_string(genesis_path).await.stack()?)
+                .stack()?;
+        let validators = genesis_validators(&genesis)
+            .stack()?
+            .into_iter()
+            .filter(|validator| validator.bonded)
+            .map(|validator| {
+                Ok((
+                    validator.moniker,
+                    validator
+                        .tokens
+                        .try_resize_to_u128()
+                        .stack_err(|| "validator tokens overflow")?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()
+            .stack()?;
+        let stake = Stake {
+            bond_denom: stacked_get!(genesis["app_state"]["staking"]["params"]["bond_denom"])
+                .as_str()
+                .stack()?
+                .to_owned(),
+            validators,
+            delegations: delegated_amounts(&genesis).stack()?,
+        };
+        (AccountExport::from_genesis(&genesis).stack()?, Some(stake))
+    } else if let Some(ref export_path) = args.export_path {
+        (AccountExport::load(export_path).await.stack()?, None)
+    } else {
+        let chain = args.profile_args.resolve(&args.chain).await.stack()?;
+        let export = AccountExport::query(&chain.grpc, &chain.chain_id, None, 16)
+            .await
+            .stack()?;
+        let validators = query_validators(&chain.grpc, &chain.prefix).await.stack()?;
+        let delegations = query_delegated_amounts(&chain.grpc, &validators)
+            .await
+            .stack()?;
+        let stake = Stake {
+            bond_denom: query_bond_denom(&chain.grpc).await.stack()?,
+            validators: validators
+                .into_iter()
+                .filter(|validator| validator.bonded())
+                .map(|validator| (validator.moniker, validator.tokens))
+                .collect(),
+            delegations,
+        };
+        (export, Some(stake))
+    };
+
+    let denom = match (&args.denom, &stake) {
+        (Some(denom), _) => denom.clone(),
+        (None, Some(stake)) => stake.bond_denom.clone(),
+        (None, None) => return Err(Error::from("need --denom for an account export")),
+    };
+    println!(
+        "{} accounts of {} at height {}",
+        export.accounts.len(),
+        export.chain_id,
+        export.height
+    );
+    print_supply(&export, time).stack()?;
+    let supply = export
+        .total_balances()
+        .stack()?
+        .get(&denom)
+        .copied()
+        .unwrap_or_default();
+    print_modules(&export, &denom, supply);
+
+    let mut holders: Vec<Holder> = export
+        .accounts
+        .iter()
+        .filter(|account| account.kind != AccountKind::Module)
+        .map(|account| Holder {
+            address: &account.address,
+            balance: account.balances.get(&denom).copied().unwrap_or_default(),
+            delegated: stake
+                .as_ref()
+                .filter(|stake| stake.bond_denom == denom)
+                .and_then(|stake| stake.delegations.get(&account.address))
+                .copied()
+                .unwrap_or_default(),
+        })
+        .filter(|holder| holder.total() > 0)
+        .collect();
+    holders.sort_by_key(|holder| std::cmp::Reverse(holder.total()));
+    print_holders(&holders, args.top, supply);
+    print_histogram(&holders, args.decimals);
+
+    match stake {
+        Some(ref stake) => print_stake(stake),
+        None => println!("an account export has no staking state, skipping the stake statistics"),
+    }
+    Ok(())
+}
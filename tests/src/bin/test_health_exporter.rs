@@ -0,0 +1,352 @@
+//! Tests the `health_exporter` metrics against an in-process gRPC stub that
+//! serves a latest block, and the client and consensus states of one IBC
+//! client. An unknown client and an unreachable chain check the failure paths.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use common::{
+    health::serve_metrics,
+    ibc::{TENDERMINT_CLIENT_STATE_URL, TENDERMINT_CONSENSUS_STATE_URL},
+    profiles::ChainProfile,
+    relayer_config::Network,
+};
+use cosmos_sdk_proto::{
+    cosmos::base::tendermint::v1beta1::{
+        service_server::{Service, ServiceServer},
+        GetBlockByHeightRequest, GetBlockByHeightResponse, GetLatestBlockRequest,
+        GetLatestBlockResponse, GetLatestValidatorSetRequest, GetLatestValidatorSetResponse,
+        GetNodeInfoRequest, GetNodeInfoResponse, GetSyncingRequest, GetSyncingResponse,
+        GetValidatorSetByHeightRequest, GetValidatorSetByHeightResponse,
+    },
+    ibc::{
+        core::client::v1::{
+            query_server::{Query, QueryServer},
+            Height, QueryClientParamsRequest, QueryClientParamsResponse, QueryClientStateRequest,
+            QueryClientStateResponse, QueryClientStatesRequest, QueryClientStatesResponse,
+            QueryClientStatusRequest, QueryClientStatusResponse, QueryConsensusStateRequest,
+            QueryConsensusStateResponse, QueryConsensusStatesRequest, QueryConsensusStatesResponse,
+            QueryUpgradedClientStateRequest, QueryUpgradedClientStateResponse,
+            QueryUpgradedConsensusStateRequest, QueryUpgradedConsensusStateResponse,
+        },
+        lightclients::tendermint::v1::{ClientState, ConsensusState},
+    },
+    tendermint::types::{Block, Header},
+};
+use log::info;
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{ensure, ensure_eq, Result, StackableErr},
+    std_init,
+};
+use prost::Message;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+const CHAIN_ID: &str = "stub-1";
+const COUNTERPARTY_CHAIN_ID: &str = "counterparty-1";
+const CLIENT_ID: &str = "07-tendermint-0";
+const LATEST_HEIGHT: i64 = 1234;
+const CLIENT_HEIGHT: u64 = 567;
+const TRUSTING_PERIOD_SECS: i64 = 24 * 60 * 60;
+
+/// The block and consensus state times are the time of the request, so that
+/// the trusting period remaining is exactly `TRUSTING_PERIOD_SECS` when the
+/// collection takes less than a second
+fn now() -> prost_types::Timestamp {
+    let now = Utc::now();
+    prost_types::Timestamp {
+        seconds: now.timestamp(),
+        nanos: now.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn any(type_url: &str, value: Vec<u8>) -> prost_types::Any {
+    prost_types::Any {
+        type_url: type_url.to_owned(),
+        value,
+    }
+}
+
+struct Stub;
+
+#[tonic::async_trait]
+impl Service for Stub {
+    async fn get_node_info(
+        &self,
+        _: Request<GetNodeInfoRequest>,
+    ) -> std::result::Result<Response<GetNodeInfoResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_syncing(
+        &self,
+        _: Request<GetSyncingRequest>,
+    ) -> std::result::Result<Response<GetSyncingResponse>, Status> {
+        Ok(Response::new(GetSyncingResponse { syncing: false }))
+    }
+
+    async fn get_latest_block(
+        &self,
+        _: Request<GetLatestBlockRequest>,
+    ) -> std::result::Result<Response<GetLatestBlockResponse>, Status> {
+        Ok(Response::new(GetLatestBlockResponse {
+            block_id: None,
+            block: Some(Block {
+                header: Some(Header {
+                    chain_id: CHAIN_ID.to_owned(),
+                    height: LATEST_HEIGHT,
+                    time: Some(now()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        }))
+    }
+
+    async fn get_block_by_height(
+        &self,
+        _: Request<GetBlockByHeightRequest>,
+    ) -> std::result::Result<Response<GetBlockByHeightResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_latest_validator_set(
+        &self,
+        _: Request<GetLatestValidatorSetRequest>,
+    ) -> std::result::Result<Response<GetLatestValidatorSetResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_validator_set_by_height(
+        &self,
+        _: Request<GetValidatorSetByHeightRequest>,
+    ) -> std::result::Result<Response<GetValidatorSetByHeightResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+}
+
+#[tonic::async_trait]
+impl Query for Stub {
+    async fn client_state(
+        &self,
+        request: Request<QueryClientStateRequest>,
+    ) -> std::result::Result<Response<QueryClientStateResponse>, Status> {
+        if request.get_ref().client_id != CLIENT_ID {
+            return Err(Status::not_found("client not found"))
+        }
+        let client_state = ClientState {
+            chain_id: COUNTERPARTY_CHAIN_ID.to_owned(),
+            trusting_period: Some(prost_types::Duration {
+                seconds: TRUSTING_PERIOD_SECS,
+                nanos: 0,
+            }),
+            latest_height: Some(Height {
+                revision_number: 1,
+                revision_height: CLIENT_HEIGHT,
+            }),
+            ..Default::default()
+        };
+        Ok(Response::new(QueryClientStateResponse {
+            client_state: Some(any(
+                TENDERMINT_CLIENT_STATE_URL,
+                client_state.encode_to_vec(),
+            )),
+            proof: vec![],
+            proof_height: None,
+        }))
+    }
+
+    async fn client_states(
+        &self,
+        _: Request<QueryClientStatesRequest>,
+    ) -> std::result::Result<Response<QueryClientStatesResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn consensus_state(
+        &self,
+        request: Request<QueryConsensusStateRequest>,
+    ) -> std::result::Result<Response<QueryConsensusStateResponse>, Status> {
+        if request.get_ref().client_id != CLIENT_ID {
+            return Err(Status::not_found("client not found"))
+        }
+        let consensus_state = ConsensusState {
+            timestamp: Some(now()),
+            ..Default::default()
+        };
+        Ok(Response::new(QueryConsensusStateResponse {
+            consensus_state: Some(any(
+                TENDERMINT_CONSENSUS_STATE_URL,
+                consensus_state.encode_to_vec(),
+            )),
+            proof: vec![],
+            proof_height: None,
+        }))
+    }
+
+    async fn consensus_states(
+        &self,
+        _: Request<QueryConsensusStatesRequest>,
+    ) -> std::result::Result<Response<QueryConsensusStatesResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn client_status(
+        &self,
+        _: Request<QueryClientStatusRequest>,
+    ) -> std::result::Result<Response<QueryClientStatusResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn client_params(
+        &self,
+        _: Request<QueryClientParamsRequest>,
+    ) -> std::result::Result<Response<QueryClientParamsResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn upgraded_client_state(
+        &self,
+        _: Request<QueryUpgradedClientStateRequest>,
+    ) -> std::result::Result<Response<QueryUpgradedClientStateResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn upgraded_consensus_state(
+        &self,
+        _: Request<QueryUpgradedConsensusStateRequest>,
+    ) -> std::result::Result<Response<QueryUpgradedConsensusStateResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+}
+
+/// Serves the stub on a free local port and returns the port
+async fn serve_stub() -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.stack()?;
+    let port = listener.local_addr().stack()?.port();
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| stream);
+        Some((stream, listener))
+    });
+    tokio::spawn(
+        Server::builder()
+            .add_service(ServiceServer::new(Stub))
+            .add_service(QueryServer::new(Stub))
+            .serve_with_incoming(incoming),
+    );
+    Ok(port)
+}
+
+fn chain_profile(chain_id: &str, grpc_port: u16, clients: &[&str]) -> ChainProfile {
+    ChainProfile {
+        chain_id: chain_id.to_owned(),
+        daemon: "onomyd".to_owned(),
+        host: "127.0.0.1".to_owned(),
+        rpc_port: None,
+        grpc_port: Some(grpc_port),
+        rest_port: None,
+        event_port: None,
+        rpc_url: None,
+        grpc_url: None,
+        event_url: None,
+        prefix: "onomy".to_owned(),
+        denom: "anom".to_owned(),
+        fee_denom: "anom".to_owned(),
+        ccv_provider: false,
+        ccv_consumer: false,
+        gas_price: None,
+        max_gas: None,
+        clients: clients.iter().map(|client| client.to_string()).collect(),
+        channels: vec![],
+    }
+}
+
+async fn http_get(addr: &str, path: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await.stack()?;
+    stream
+        .write_all(format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").as_bytes())
+        .await
+        .stack()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.stack()?;
+    Ok(response)
+}
+
+/// Finds the value of the sample that starts with `sample`
+fn sample_value(metrics: &str, sample: &str) -> Result<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(sample))
+        .stack_err(|| format!("no sample {sample} in:\n{metrics}"))?
+        .trim()
+        .parse()
+        .stack()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+
+    let port = serve_stub().await.stack()?;
+    // give the stub server time to start
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let network = Network {
+        chains: vec![
+            chain_profile(CHAIN_ID, port, &[CLIENT_ID, "07-tendermint-404"]),
+            // nothing is listening here
+            chain_profile("unreachable", 1, &[]),
+        ],
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await.stack()?;
+    let addr = listener.local_addr().stack()?.to_string();
+    let server = tokio::spawn(serve_metrics(listener, network, None));
+
+    let response = http_get(&addr, "/metrics").await.stack()?;
+    info!("{response}");
+    ensure!(response.starts_with("HTTP/1.1 200 OK"));
+    let value = |sample: &str| sample_value(&response, sample);
+    let chain = format!("{{chain_id=\"{CHAIN_ID}\"}}");
+    ensure_eq!(value(&format!("onomy_chain_up{chain}"))?, 1.0);
+    ensure_eq!(value(&format!("onomy_chain_syncing{chain}"))?, 0.0);
+    ensure_eq!(
+        value(&format!("onomy_chain_latest_height{chain}"))?,
+        LATEST_HEIGHT as f64
+    );
+    // the block time is when the stub was queried, after the collection started
+    let block_age = value(&format!("onomy_chain_block_age_seconds{chain}"))?;
+    ensure!((-1.0 < block_age) && (block_age <= 0.0));
+
+    ensure_eq!(
+        value(&format!(
+            "onomy_ibc_client_up{{chain_id=\"{CHAIN_ID}\",client_id=\"{CLIENT_ID}\"}}"
+        ))?,
+        1.0
+    );
+    let client = format!(
+        "{{chain_id=\"{CHAIN_ID}\",client_id=\"{CLIENT_ID}\",counterparty_chain_id=\"\
+         {COUNTERPARTY_CHAIN_ID}\"}}"
+    );
+    ensure_eq!(
+        value(&format!(
+            "onomy_ibc_client_trusting_seconds_remaining{client}"
+        ))?,
+        TRUSTING_PERIOD_SECS as f64
+    );
+    ensure_eq!(value(&format!("onomy_ibc_client_frozen{client}"))?, 0.0);
+    ensure_eq!(
+        value(&format!(
+            "onomy_ibc_client_up{{chain_id=\"{CHAIN_ID}\",client_id=\"07-tendermint-404\"}}"
+        ))?,
+        0.0
+    );
+    ensure_eq!(value("onomy_chain_up{chain_id=\"unreachable\"}")?, 0.0);
+
+    let response = http_get(&addr, "/").await.stack()?;
+    ensure!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Reconciles the bonded validator set of the provider with the CCV validator
+//! set of every `ccv_consumer` chain of `--network` in
+//! `tests/configs/networks.ron`. `--node`, `--grpc`, and `--chain-id` override
+//! the chain selected with `--chain`.
+//!
+//! Provider validators are paired with consumer validators through the
+//! consumer keys that they assigned on the provider, validators that did not
+//! assign a key are expected to use their provider key on the consumer. This
+//! reports bonded provider validators that are missing on a consumer,
+//! consumer validators that match no provider validator, and power mismatches.
+//! Power changes take a VSC packet round trip to reach the consumer, so
+//! mismatches are queried again after `--vsc-lag-secs` and only those that
+//! persist are reported. Validators that have not assigned a consumer key are
+//! listed as a warning.
+//!
+//! Consumers with a governance staking module should also have a bonded
+//! consumer-side validator for every bonded provider validator, with the same
+//! operator account under the consumer prefix. Provider validators without one
+//! are reported, and consumers without a staking module are skipped for this
+//! check. This exits with an
+//! error listing the problems if there are any.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin validator_sets -- --network testnet
+
+cargo r --bin validator_sets -- --network testnet --chain onex --vsc-lag-secs 300
+
+*/
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use clap::Parser;
+use common::{
+    profiles::{ProfileArgs, ResolvedChain},
+    validators::{
+        query_assigned_consumer_keys, query_consensus_validators, query_validators, ValidatorInfo,
+    },
+    GREEN, RED, RESET, YELLOW,
+};
+use log::warn;
+use onomy_test_lib::{
+    reprefix_bech32,
+    super_orchestrator::{
+        stacked_errors::{Error, Result, StackableErr},
+        std_init,
+    },
+};
+use tokio::time::sleep;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct SetsArgs {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// The provider chain of the network, the other chains are its consumers
+    #[arg(long, default_value_t = String::from("onomy"))]
+    pub provider: String,
+    /// Only reconcile this consumer of the network
+    #[arg(long)]
+    pub chain: Option<String>,
+    /// How long to wait before querying power mismatches again, this should
+    /// be longer than it takes for a VSC packet to be relayed
+    #[arg(long, default_value_t = 120)]
+    pub vsc_lag_secs: u64,
+}
+
+/// A bonded provider validator and the consumer address it is expected at
+struct Pairing {
+    validator: ValidatorInfo,
+    /// The `{consumer_prefix}valcons` address
+    consumer_address: String,
+    assigned_key: bool,
+}
+
+/// The voting powers of the provider and consumer validator sets, keyed by
+/// their consensus addresses
+async fn query_powers(
+    provider: &ResolvedChain,
+    consumer: &ResolvedChain,
+) -> Result<(BTreeMap<String, i64>, BTreeMap<String, i64>)> {
+    let provider_powers = query_consensus_validators(&provider.grpc)
+        .await
+        .stack()?
+        .into_iter()
+        .map(|validator| (validator.address, validator.voting_power))
+        .collect();
+    let consumer_powers = query_consensus_validators(&consumer.grpc)
+        .await
+        .stack()?
+        .into_iter()
+        .map(|validator| (validator.address, validator.voting_power))
+        .collect();
+    Ok((provider_powers, consumer_powers))
+}
+
+/// Returns the consumer addresses of the pairings whose power differs
+fn power_mismatches(
+    pairings: &[Pairing],
+    provider_powers: &BTreeMap<String, i64>,
+    consumer_powers: &BTreeMap<String, i64>,
+) -> BTreeSet<String> {
+    let mut mismatches = BTreeSet::new();
+    for pairing in pairings {
+        let provider_power = provider_powers.get(&pairing.validator.consensus_address);
+        let consumer_power = consumer_powers.get(&pairing.consumer_address);
+        if let (Some(provider_power), Some(consumer_power)) = (provider_power, consumer_power) {
+            if provider_power != consumer_power {
+                mismatches.insert(pairing.consumer_address.clone());
+            }
+        }
+    }
+    mismatches
+}
+
+async fn reconcile(
+    args: &SetsArgs,
+    provider: &ResolvedChain,
+    validators: &[ValidatorInfo],
+    consumer: &ResolvedChain,
+    problems: &mut Vec<String>,
+) -> Result<()> {
+    let keys = query_assigned_consumer_keys(&provider.grpc, &consumer.chain_id, validators)
+        .await
+        .stack()?;
+    let mut pairings = vec![];
+    for (validator, key) in validators.iter().zip(keys) {
+        if !validator.bonded() {
+            continue
+        }
+        let assigned_key = key.is_some();
+        let consumer_address = key.unwrap_or_else(|| validator.consensus_address.clone());
+        pairings.push(Pairing {
+            validator: validator.clone(),
+            consumer_address: reprefix_bech32(
+                &consumer_address,
+                &format!("{}valcons", consumer.prefix),
+            )
+            .stack()?,
+            assigned_key,
+        });
+    }
+
+    let (mut provider_powers, mut consumer_powers) =
+        query_powers(provider, consumer).await.stack()?;
+    let mut mismatches = power_mismatches(&pairings, &provider_powers, &consumer_powers);
+    if !mismatches.is_empty() {
+        println!(
+            "{}: {} power mismatches, querying again in {}s to account for pending VSC packets",
+            consumer.chain_id,
+            mismatches.len(),
+            args.vsc_lag_secs
+        );
+        sleep(Duration::from_secs(args.vsc_lag_secs)).await;
+        (provider_powers, consumer_powers) = query_powers(provider, consumer).await.stack()?;
+        let persisting = power_mismatches(&pairings, &provider_powers, &consumer_powers);
+        mismatches = mismatches.intersection(&persisting).cloned().collect();
+    }
+
+    println!(
+        "{}: {} bonded provider validators, {} consumer validators",
+        consumer.chain_id,
+        pairings.len(),
+        consumer_powers.len()
+    );
+    println!(
+        "  {:<24} {:<52} {:>14} {:>14} {:<12}",
+        "moniker", "operator", "provider power", "consumer power", "consumer key"
+    );
+    for pairing in &pairings {
+        let provider_power = provider_powers.get(&pairing.validator.consensus_address);
+        let consumer_power = consumer_powers.get(&pairing.consumer_address);
+        let missing = consumer_power.is_none();
+        let mismatch = mismatches.contains(&pairing.consumer_address);
+        let color = if missing || mismatch {
+            RED
+        } else if !pairing.assigned_key {
+            YELLOW
+        } else {
+            GREEN
+        };
+        let format_power =
+            |power: Option<&i64>| power.map_or_else(|| "-".to_owned(), |power| power.to_string());
+        println!(
+            "{color}  {:<24} {:<52} {:>14} {:>14} {:<12}{RESET}",
+            pairing
+                .validator
+                .moniker
+                .chars()
+                .take(24)
+                .collect::<String>(),
+            pairing.validator.operator_address,
+            format_power(provider_power),
+            format_power(consumer_power),
+            if pairing.assigned_key {
+                "assigned"
+            } else {
+                "provider key"
+            }
+        );
+        let name = format!(
+            "{} ({})",
+            pairing.validator.moniker, pairing.validator.operator_address
+        );
+        if missing {
+            problems.push(format!(
+                "{name} is bonded on the provider but missing from the validator set of {} at {}",
+                consumer.chain_id, pairing.consumer_address
+            ));
+        } else if mismatch {
+            problems.push(format!(
+                "{name} has power {} on the provider but {} on {} after waiting for the VSC lag",
+                format_power(provider_power),
+                format_power(consumer_power),
+                consumer.chain_id
+            ));
+        }
+    }
+
+    let unassigned: Vec<&str> = pairings
+        .iter()
+        .filter(|pairing| !pairing.assigned_key)
+        .map(|pairing| pairing.validator.moniker.as_str())
+        .collect();
+    if !unassigned.is_empty() {
+        println!(
+            "{YELLOW}{} validators have not assigned a consumer key on {} and use their provider \
+             key: {unassigned:?}{RESET}",
+            unassigned.len(),
+            consumer.chain_id
+        );
+    }
+
+    let paired: BTreeSet<&String> = pairings
+        .iter()
+        .map(|pairing| &pairing.consumer_address)
+        .collect();
+    for (address, power) in &consumer_powers {
+        if !paired.contains(address) {
+            println!("{RED}  unknown consumer validator {address} with power {power}{RESET}");
+            problems.push(format!(
+                "{address} with power {power} on {} matches no bonded provider validator",
+                consumer.chain_id
+            ));
+        }
+    }
+
+    // consumer-side validators of the governance staking module, paired with
+    // the bonded provider validators by their operator account
+    match query_validators(&consumer.grpc, &consumer.prefix).await {
+        Ok(governance_validators) => {
+            let governance_validators: BTreeMap<&str, &ValidatorInfo> = governance_validators
+                .iter()
+                .map(|validator| (validator.operator_address.as_str(), validator))
+                .collect();
+            let mut unmatched = 0;
+            for pairing in &pairings {
+                let operator = reprefix_bech32(
+                    &pairing.validator.operator_address,
+                    &format!("{}valoper", consumer.prefix),
+                )
+                .stack()?;
+                let status = match governance_validators.get(operator.as_str()) {
+                    Some(validator) if validator.bonded() => continue,
+                    Some(validator) => format!("{:?}", validator.status),
+                    None => "missing".to_owned(),
+                };
+                unmatched += 1;
+                println!(
+                    "{RED}  {:<24} {:<52} {status}{RESET}",
+                    pairing
+                        .validator
+                        .moniker
+                        .chars()
+                        .take(24)
+                        .collect::<String>(),
+                    operator
+                );
+                problems.push(format!(
+                    "{} ({}) is bonded on the provider but its governance validator {operator} on \
+                     {} is {status}",
+                    pairing.validator.moniker,
+                    pairing.validator.operator_address,
+                    consumer.chain_id
+                ));
+            }
+            println!(
+                "{}: {} governance validators, {unmatched} bonded provider validators without one",
+                consumer.chain_id,
+                governance_validators.len()
+            );
+        }
+        Err(e) => {
+            warn!(
+                "skipping governance validators of {}, it may have no staking module: {e:?}",
+                consumer.chain_id
+            );
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = SetsArgs::parse();
+
+    let network = args.profile_args.network_profile().await.stack()?;
+    let provider = args
+        .profile_args
+        .resolve_in(&network, &args.provider, args.chain.as_deref())
+        .stack()?;
+    let validators = query_validators(&provider.grpc, &provider.prefix)
+        .await
+        .stack()?;
+
+    let consumers: Vec<&String> = network
+        .chains
+        .iter()
+        .filter(|(name, chain)| chain.ccv_consumer && (*name != &args.provider))
+        .map(|(name, _)| name)
+        .collect();
+    if consumers.is_empty() {
+        return Err(Error::from(format!(
+            "network {} has no chain with `ccv_consumer: true`, its consumers need to be added to \
+             the networks profile",
+            args.profile_args.network.as_deref().unwrap_or_default()
+        )))
+    }
+
+    let mut problems = vec![];
+    for name in consumers {
+        if args.chain.as_ref().map_or(false, |only| only != name) {
+            continue
+        }
+        let consumer = args
+            .profile_args
+            .resolve_in(&network, name, args.chain.as_deref())
+            .stack()?;
+        reconcile(&args, &provider, &validators, &consumer, &mut problems)
+            .await
+            .stack()?;
+    }
+
+    if problems.is_empty() {
+        println!("{GREEN}the validator sets are reconciled{RESET}");
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "validator set problems:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
@@ -0,0 +1,220 @@
+//! Reports the missed blocks of every validator within the signed blocks
+//! window, on every chain of `--network` in `tests/configs/networks.ron`, so
+//! that validators at risk of being jailed for downtime can be warned
+//!
+//! The signing infos of each chain are matched to the provider's validators by
+//! consensus address, on `ccv_consumer` chains this goes through the consumer
+//! keys that validators assigned on the provider. Chains that are not ICS
+//! chains are matched to their own staking validators. Validators that are not
+//! bonded are only listed with `--all`, unless they are jailed or tombstoned.
+//! This exits with an error if a bonded validator has less than
+//! `--min-left-percent` of its allowed missed blocks left. `--node`, `--grpc`,
+//! and `--chain-id` override the chain selected with `--chain`.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin validator_uptime -- --network testnet
+
+cargo r --bin validator_uptime -- --network testnet --chain onex --all
+
+*/
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use clap::Parser;
+use common::{
+    ibc::format_duration,
+    profiles::ProfileArgs,
+    validators::{
+        consumer_address_map, query_signing_infos, query_slashing_params, query_validators,
+        SlashingParams, ValidatorInfo,
+    },
+    GREEN, RED, RESET, YELLOW,
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct UptimeArgs {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// The provider chain of the network, the `ccv_consumer` chains are its
+    /// consumers
+    #[arg(long, default_value_t = String::from("onomy"))]
+    pub provider: String,
+    /// Only report this chain of the network
+    #[arg(long)]
+    pub chain: Option<String>,
+    /// Also list validators that are not bonded
+    #[arg(long)]
+    pub all: bool,
+    /// Fail if a bonded validator has less than this percent of its allowed
+    /// missed blocks left
+    #[arg(long, default_value_t = 10.0)]
+    pub min_left_percent: f64,
+}
+
+struct Row {
+    moniker: String,
+    operator_address: String,
+    consensus_address: String,
+    missed: u64,
+    left: u64,
+    left_percent: f64,
+    status: &'static str,
+    bonded: bool,
+}
+
+fn print_params(chain_id: &str, params: &SlashingParams) -> Result<()> {
+    let jail_duration = chrono::Duration::from_std(params.downtime_jail_duration).stack()?;
+    println!(
+        "{chain_id}: signed blocks window of {}, jailed after missing more than {} blocks, jailed \
+         for {}",
+        params.signed_blocks_window,
+        params.max_missed_blocks(),
+        format_duration(jail_duration)
+    );
+    Ok(())
+}
+
+/// Maps the consensus addresses of `validators` to their index
+fn address_map(validators: &[ValidatorInfo]) -> BTreeMap<String, usize> {
+    validators
+        .iter()
+        .enumerate()
+        .map(|(i, validator)| (validator.consensus_address.clone(), i))
+        .collect()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = UptimeArgs::parse();
+
+    let network = args.profile_args.network_profile().await.stack()?;
+    let provider = args
+        .profile_args
+        .resolve_in(&network, &args.provider, args.chain.as_deref())
+        .stack()?;
+    let validators = query_validators(&provider.grpc, &provider.prefix)
+        .await
+        .stack()?;
+
+    let now = Utc::now();
+    let mut problems = vec![];
+    for (name, profile) in &network.chains {
+        if args.chain.as_ref().map_or(false, |only| only != name) {
+            continue
+        }
+        let chain = args
+            .profile_args
+            .resolve_in(&network, name, args.chain.as_deref())
+            .stack()?;
+        let own_validators;
+        let (chain_validators, address_map) = if name == &args.provider {
+            (&validators, address_map(&validators))
+        } else if profile.ccv_consumer {
+            (
+                &validators,
+                consumer_address_map(&provider.grpc, &chain.chain_id, &chain.prefix, &validators)
+                    .await
+                    .stack()?,
+            )
+        } else {
+            own_validators = query_validators(&chain.grpc, &chain.prefix).await.stack()?;
+            (&own_validators, address_map(&own_validators))
+        };
+        let params = query_slashing_params(&chain.grpc).await.stack()?;
+        let max_missed = params.max_missed_blocks();
+        print_params(&chain.chain_id, &params).stack()?;
+
+        let mut rows = vec![];
+        for info in query_signing_infos(&chain.grpc).await.stack()? {
+            let validator = address_map
+                .get(&info.consensus_address)
+                .map(|i| &chain_validators[*i]);
+            // validators that cannot be matched are assumed to be bonded
+            let bonded = validator.map_or(true, |validator| validator.bonded());
+            let left = max_missed.saturating_sub(info.missed_blocks_counter);
+            let status = if info.tombstoned {
+                "tombstoned"
+            } else if info.jailed_at(now) || validator.map_or(false, |validator| validator.jailed) {
+                "jailed"
+            } else if !bonded {
+                "not bonded"
+            } else {
+                "active"
+            };
+            if (status == "not bonded") && !args.all {
+                continue
+            }
+            rows.push(Row {
+                moniker: validator.map_or_else(|| "?".to_owned(), |v| v.moniker.clone()),
+                operator_address: validator
+                    .map_or_else(|| "?".to_owned(), |v| v.operator_address.clone()),
+                consensus_address: info.consensus_address.clone(),
+                missed: info.missed_blocks_counter,
+                left,
+                left_percent: if max_missed == 0 {
+                    0.0
+                } else {
+                    (left as f64) * 100.0 / (max_missed as f64)
+                },
+                status,
+                bonded,
+            });
+        }
+        rows.sort_by(|a, b| a.left_percent.total_cmp(&b.left_percent));
+
+        println!(
+            "  {:<24} {:<52} {:>8} {:>8} {:>7} {:<10}",
+            "moniker", "operator", "missed", "left", "left %", "status"
+        );
+        for row in &rows {
+            let at_risk = (row.status == "active") && (row.left_percent < args.min_left_percent);
+            let color = if at_risk || (row.status == "jailed") || (row.status == "tombstoned") {
+                RED
+            } else if row.missed > 0 {
+                YELLOW
+            } else {
+                GREEN
+            };
+            println!(
+                "{color}  {:<24} {:<52} {:>8} {:>8} {:>6.1}% {:<10}{RESET}",
+                row.moniker.chars().take(24).collect::<String>(),
+                row.operator_address,
+                row.missed,
+                row.left,
+                row.left_percent,
+                row.status
+            );
+            if at_risk && row.bonded {
+                problems.push(format!(
+                    "{} ({}, {}) on {} has missed {} blocks and has {} left",
+                    row.moniker,
+                    row.operator_address,
+                    row.consensus_address,
+                    chain.chain_id,
+                    row.missed,
+                    row.left
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        println!("{GREEN}no validators are at risk of being jailed{RESET}");
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "validators at risk of being jailed:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
@@ -0,0 +1,255 @@
+//! Computes the vested, unvested, delegated free, and delegated vesting
+//! amounts of vesting accounts at `--at`, with the same math as the SDK, along
+//! with the locked and spendable amounts and the next unlock. This is for
+//! answering holders of the `PeriodicVestingAccount`s that
+//! `process_exported_genesis` creates.
+//!
+//! The accounts come from `--genesis-path`, from `--export-path` (a JSON
+//! export of `query_accounts`), or else from the live `--chain` of `--network`
+//! in `tests/configs/networks.ron`. With `--address` only that account is
+//! looked up and its schedule is printed, otherwise the totals are printed and
+//! every vesting account is written to `--output` as CSV.
+
+#[rustfmt::skip]
+/*
+e.x.
+
+cargo r --bin vesting_status -- --network testnet --address onomy1y3c6q58vvuxr5tcmesay74wvhrey3pqv8g6y3r
+
+cargo r --bin vesting_status -- --genesis-path ./../environments/mainnet/onex-mainnet/genesis.json --at 2024-06-01T00:00:00Z
+
+cargo r --bin vesting_status -- --network testnet --chain onex
+
+*/
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use clap::Parser;
+use common::{
+    accounts::{add_coins, coins_to_string, query_account, Account, AccountExport, Coins},
+    profiles::ProfileArgs,
+};
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    std_init, FileOptions,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+struct VestingArgs {
+    // `--genesis-path` selects a genesis file to use the accounts of
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    /// The chain of `--network` to query, if no file is given
+    #[arg(long, default_value_t = String::from("onex"))]
+    pub chain: String,
+    /// Use the accounts of this `query_accounts` JSON export
+    #[arg(long)]
+    pub export_path: Option<String>,
+    /// Only look up this address
+    #[arg(long)]
+    pub address: Option<String>,
+    /// The time to compute the status at, in RFC 3339 e.x.
+    /// "2024-06-01T00:00:00Z", defaults to now
+    #[arg(long)]
+    pub at: Option<String>,
+    #[arg(long, default_value_t = String::from("./tests/logs/vesting_status.csv"))]
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatusRow {
+    address: String,
+    #[serde(rename = "type")]
+    kind: String,
+    vested: String,
+    unvested: String,
+    delegated_free: String,
+    delegated_vesting: String,
+    locked: String,
+    spendable: String,
+    next_unlock_time: String,
+    next_unlock_amount: String,
+}
+
+fn format_time(time: i64) -> String {
+    Utc.timestamp_opt(time, 0)
+        .single()
+        .map_or_else(|| time.to_string(), |time| time.to_rfc3339())
+}
+
+fn print_account(account: &Account, time: i64) -> Result<()> {
+    let status = account.vesting_status(time).stack_err(|| {
+        format!(
+            "{} is a {}, not a vesting account",
+            account.address, account.kind
+        )
+    })?;
+    // `vesting_status` is only `Some` for vesting accounts
+    let vesting = account.vesting.as_ref().stack()?;
+    println!("{} ({})", account.address, account.kind);
+    for (name, coins) in [
+        ("balance", &account.balances),
+        ("original vesting", &vesting.original_vesting),
+        ("vested", &status.vested),
+        ("unvested", &status.unvested),
+        ("delegated free", &status.delegated_free),
+        ("delegated vesting", &status.delegated_vesting),
+        ("locked", &status.locked),
+        ("spendable", &status.spendable),
+    ] {
+        println!("  {name:<18} {}", coins_to_string(coins));
+    }
+    if let Some(start_time) = vesting.start_time {
+        println!("  {:<18} {}", "start", format_time(start_time));
+    }
+    println!("  {:<18} {}", "end", format_time(vesting.end_time));
+    match status.next_unlock {
+        Some((unlock_time, ref coins)) => println!(
+            "  {:<18} {} at {}",
+            "next unlock",
+            coins_to_string(coins),
+            format_time(unlock_time)
+        ),
+        None => println!("  {:<18} none", "next unlock"),
+    }
+    if !vesting.periods.is_empty() {
+        println!("  periods:");
+        let mut period_end = vesting.start_time.unwrap_or_default();
+        for period in &vesting.periods {
+            period_end += period.length;
+            println!(
+                "    {} {:<40} {}",
+                format_time(period_end),
+                coins_to_string(&period.amount),
+                if period_end <= time {
+                    "unlocked"
+                } else {
+                    "locked"
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    std_init()?;
+    let args = VestingArgs::parse();
+
+    let time = match args.at {
+        Some(ref at) => DateTime::parse_from_rfc3339(at)
+            .stack_err(|| format!("invalid --at {at}"))?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    }
+    .timestamp();
+
+    let accounts = if let Some(ref genesis_path) = args.profile_args.args.genesis_path {
+        let genesis: Value =
+            serde_json::from_str(&FileOptions::read_to_string(genesis_path).await.stack()?)
+                .stack()?;
+        AccountExport::from_genesis(&genesis).stack()?.accounts
+    } else if let Some(ref export_path) = args.export_path {
+        AccountExport::load(export_path).await.stack()?.accounts
+    } else {
+        let chain = args.profile_args.resolve(&args.chain).await.stack()?;
+        if let Some(ref address) = args.address {
+            // a single live lookup does not need every account
+            vec![query_account(&chain.grpc, address).await.stack()?]
+        } else {
+            AccountExport::query(&chain.grpc, &chain.chain_id, None, 16)
+                .await
+                .stack()?
+                .accounts
+        }
+    };
+
+    if let Some(ref address) = args.address {
+        let account = accounts
+            .iter()
+            .find(|account| &account.address == address)
+            .stack_err(|| format!("there is no account {address}"))?;
+        return print_account(account, time).stack()
+    }
+
+    let mut rows = vec![];
+    let mut totals = BTreeMap::<&str, Coins>::new();
+    // unlocks by time
+    let mut unlocks = BTreeMap::<i64, Coins>::new();
+    for account in &accounts {
+        let status = match account.vesting_status(time) {
+            Some(status) => status,
+            None => continue,
+        };
+        for (name, coins) in [
+            ("vested", &status.vested),
+            ("unvested", &status.unvested),
+            ("delegated free", &status.delegated_free),
+            ("delegated vesting", &status.delegated_vesting),
+            ("locked", &status.locked),
+            ("spendable", &status.spendable),
+        ] {
+            add_coins(totals.entry(name).or_default(), coins);
+        }
+        if let Some((unlock_time, ref coins)) = status.next_unlock {
+            add_coins(unlocks.entry(unlock_time).or_default(), coins);
+        }
+        rows.push(StatusRow {
+            address: account.address.clone(),
+            kind: account.kind.to_string(),
+            vested: coins_to_string(&status.vested),
+            unvested: coins_to_string(&status.unvested),
+            delegated_free: coins_to_string(&status.delegated_free),
+            delegated_vesting: coins_to_string(&status.delegated_vesting),
+            locked: coins_to_string(&status.locked),
+            spendable: coins_to_string(&status.spendable),
+            next_unlock_time: status
+                .next_unlock
+                .as_ref()
+                .map_or_else(String::new, |(unlock_time, _)| format_time(*unlock_time)),
+            next_unlock_amount: status
+                .next_unlock
+                .as_ref()
+                .map_or_else(String::new, |(_, coins)| coins_to_string(coins)),
+        });
+    }
+    if rows.is_empty() {
+        return Err(Error::from("there are no vesting accounts"))
+    }
+
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    for row in &rows {
+        wtr.serialize(row).stack()?;
+    }
+    FileOptions::write_str(
+        &args.output,
+        &String::from_utf8(wtr.into_inner().stack()?).stack()?,
+    )
+    .await
+    .stack()?;
+
+    println!(
+        "{} vesting accounts at {}, written to {}",
+        rows.len(),
+        format_time(time),
+        args.output
+    );
+    for (name, coins) in &totals {
+        println!("  {name:<18} {}", coins_to_string(coins));
+    }
+    match unlocks.iter().next() {
+        Some((unlock_time, coins)) => println!(
+            "  {:<18} {} at {}",
+            "next unlock",
+            coins_to_string(coins),
+            format_time(*unlock_time)
+        ),
+        None => println!("  {:<18} none", "next unlock"),
+    }
+    Ok(())
+}
@@ -0,0 +1,1008 @@
+//! Paginated export of every account of a chain over gRPC, with the account
+//! types decoded and the bank balances joined in
+
+use std::collections::BTreeMap;
+
+use cosmos_sdk_proto::cosmos::{
+    auth::v1beta1::{
+        query_client::QueryClient as AuthQueryClient, BaseAccount, ModuleAccount,
+        QueryAccountRequest, QueryAccountsRequest,
+    },
+    bank::v1beta1::{query_client::QueryClient as BankQueryClient, QueryAllBalancesRequest},
+    base::{query::v1beta1::PageRequest, v1beta1::Coin},
+    vesting::v1beta1::{
+        BaseVestingAccount, ContinuousVestingAccount, DelayedVestingAccount,
+        PeriodicVestingAccount, PermanentLockedAccount,
+    },
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use log::warn;
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    FileOptions,
+};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tonic::{metadata::MetadataMap, transport::Channel};
+
+use crate::{
+    genesis::{account_type, coins_to_value, parse_coins},
+    validators::DEC_ONE,
+};
+
+/// The gRPC header that selects the height of a query, and that the node sets
+/// on responses to the height that was queried
+pub const BLOCK_HEIGHT_HEADER: &str = "x-cosmos-block-height";
+
+/// Coins by denom
+pub type Coins = BTreeMap<String, u128>;
+
+/// (De)serializes `Coins` as the SDK JSON of a list of coins
+mod sdk_coins {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    use super::{coins_to_value, parse_coins, Coins};
+
+    pub fn serialize<S: Serializer>(coins: &Coins, serializer: S) -> Result<S::Ok, S::Error> {
+        coins_to_value(coins).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Coins, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        parse_coins(&value).map_err(|e| D::Error::custom(format!("{e:?}")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountKind {
+    Base,
+    Module,
+    ContinuousVesting,
+    DelayedVesting,
+    PeriodicVesting,
+    PermanentLocked,
+    /// An account type of some other module, e.x.
+    /// "/ethermint.types.v1.EthAccount", of which only the base account is
+    /// known
+    Other(String),
+}
+
+impl AccountKind {
+    /// The `@type` of the account, e.x. "/cosmos.auth.v1beta1.BaseAccount"
+    pub fn type_url(&self) -> &str {
+        match self {
+            AccountKind::Base => "/cosmos.auth.v1beta1.BaseAccount",
+            AccountKind::Module => "/cosmos.auth.v1beta1.ModuleAccount",
+            AccountKind::ContinuousVesting => "/cosmos.vesting.v1beta1.ContinuousVestingAccount",
+            AccountKind::DelayedVesting => "/cosmos.vesting.v1beta1.DelayedVestingAccount",
+            AccountKind::PeriodicVesting => "/cosmos.vesting.v1beta1.PeriodicVestingAccount",
+            AccountKind::PermanentLocked => "/cosmos.vesting.v1beta1.PermanentLockedAccount",
+            AccountKind::Other(type_url) => type_url,
+        }
+    }
+
+    pub fn from_type_url(type_url: &str) -> Self {
+        [
+            AccountKind::Base,
+            AccountKind::Module,
+            AccountKind::ContinuousVesting,
+            AccountKind::DelayedVesting,
+            AccountKind::PeriodicVesting,
+            AccountKind::PermanentLocked,
+        ]
+        .into_iter()
+        .find(|kind| kind.type_url() == type_url)
+        .unwrap_or_else(|| AccountKind::Other(type_url.to_owned()))
+    }
+}
+
+impl std::fmt::Display for AccountKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.type_url().rsplit('.').next().unwrap_or_default())
+    }
+}
+
+/// The accounts of other modules usually embed the base account as their first
+/// field, like `ModuleAccount` does
+#[derive(Clone, PartialEq, Message)]
+struct EmbeddedBaseAccount {
+    #[prost(message, optional, tag = "1")]
+    base_account: Option<BaseAccount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingPeriod {
+    /// In seconds
+    pub length: i64,
+    #[serde(with = "sdk_coins")]
+    pub amount: Coins,
+}
+
+/// The `BaseVestingAccount` fields and the schedule of a vesting account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vesting {
+    #[serde(with = "sdk_coins")]
+    pub original_vesting: Coins,
+    #[serde(with = "sdk_coins")]
+    pub delegated_free: Coins,
+    #[serde(with = "sdk_coins")]
+    pub delegated_vesting: Coins,
+    /// UNIX time in seconds, not used by delayed and permanent locked accounts
+    pub start_time: Option<i64>,
+    /// UNIX time in seconds
+    pub end_time: i64,
+    /// Only used by periodic vesting accounts
+    pub periods: Vec<VestingPeriod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub address: String,
+    pub kind: AccountKind,
+    pub account_number: u64,
+    pub sequence: u64,
+    /// The name of module accounts
+    pub module_name: Option<String>,
+    pub vesting: Option<Vesting>,
+    #[serde(with = "sdk_coins")]
+    pub balances: Coins,
+}
+
+/// A CSV row of an `Account`, coins are formatted like "100anom,5abtc"
+#[derive(Debug, Clone, Serialize)]
+struct AccountRow<'a> {
+    address: &'a str,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    account_number: u64,
+    sequence: u64,
+    module_name: &'a str,
+    balances: String,
+    original_vesting: String,
+    delegated_free: String,
+    delegated_vesting: String,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+}
+
+/// All the accounts of a chain at one height
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub chain_id: String,
+    pub height: u64,
+    pub accounts: Vec<Account>,
+}
+
+/// Formats coins the way the SDK does, e.x. "100anom,5abtc"
+pub fn coins_to_string(coins: &Coins) -> String {
+    coins
+        .iter()
+        .map(|(denom, amount)| format!("{amount}{denom}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn proto_coins(coins: &[Coin]) -> Result<Coins> {
+    let mut res = Coins::new();
+    for coin in coins {
+        let amount: u128 = coin
+            .amount
+            .parse()
+            .stack_err(|| format!("invalid amount of {}", coin.denom))?;
+        let total = res.entry(coin.denom.clone()).or_default();
+        *total = total
+            .checked_add(amount)
+            .stack_err(|| "coin amount overflow")?;
+    }
+    Ok(res)
+}
+
+/// Subtracts `rhs` from `lhs` per denom, saturating at zero and dropping
+/// zero amounts
+pub fn saturating_sub_coins(lhs: &Coins, rhs: &Coins) -> Coins {
+    lhs.iter()
+        .map(|(denom, amount)| {
+            (
+                denom.clone(),
+                amount.saturating_sub(rhs.get(denom).copied().unwrap_or_default()),
+            )
+        })
+        .filter(|(_, amount)| *amount != 0)
+        .collect()
+}
+
+/// Adds `rhs` to `lhs` per denom
+pub fn add_coins(lhs: &mut Coins, rhs: &Coins) {
+    for (denom, amount) in rhs {
+        *lhs.entry(denom.clone()).or_default() += amount;
+    }
+}
+
+/// Rounds `quo + (rem / div)` to an integer, with the banker's rounding of
+/// `sdk.Dec`
+fn round_half_even(quo: u128, rem: u128, div: u128) -> u128 {
+    let twice = rem * 2;
+    if (twice > div) || ((twice == div) && (quo % 2 == 1)) {
+        quo + 1
+    } else {
+        quo
+    }
+}
+
+/// `sdk.NewDec(x).Quo(sdk.NewDec(y))` scaled by 10^18, for `x <= y`
+fn dec_quo(x: u128, y: u128) -> u128 {
+    let scaled = x * DEC_ONE;
+    let (quo, rem) = (scaled / y, scaled % y);
+    round_half_even(quo, rem * DEC_ONE / y, DEC_ONE)
+}
+
+/// `amount.ToDec().Mul(dec).RoundInt()` for a `dec` of at most one, scaled by
+/// 10^18
+fn dec_mul_round_int(amount: u128, dec: u128) -> u128 {
+    let (high, low) = (amount / DEC_ONE, amount % DEC_ONE);
+    let product = low * dec;
+    round_half_even(high * dec + (product / DEC_ONE), product % DEC_ONE, DEC_ONE)
+}
+
+/// Parses a genesis integer, which is usually a string
+fn json_int<T: std::str::FromStr>(value: &Value) -> Result<T> {
+    let s = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(number) => number.to_string(),
+        _ => return Err(Error::from(format!("expected an integer, found {value}"))),
+    };
+    s.parse()
+        .map_err(|_| Error::from(format!("invalid integer {s}")))
+}
+
+fn json_field<'a>(value: &'a Value, pointer: &str) -> Result<&'a Value> {
+    value
+        .pointer(pointer)
+        .stack_err(|| format!("account has no {pointer}"))
+}
+
+fn page_request(key: Vec<u8>) -> Option<PageRequest> {
+    Some(PageRequest {
+        key,
+        offset: 0,
+        limit: 1000,
+        count_total: false,
+        reverse: false,
+    })
+}
+
+/// Wraps `message` in a request for the state at `height`, or the latest state
+fn request_at<T>(message: T, height: Option<u64>) -> Result<tonic::Request<T>> {
+    let mut request = tonic::Request::new(message);
+    if let Some(height) = height {
+        request
+            .metadata_mut()
+            .insert(BLOCK_HEIGHT_HEADER, height.to_string().parse().stack()?);
+    }
+    Ok(request)
+}
+
+fn response_height(metadata: &MetadataMap) -> Option<u64> {
+    metadata
+        .get(BLOCK_HEIGHT_HEADER)
+        .and_then(|height| height.to_str().ok())
+        .and_then(|height| height.parse().ok())
+}
+
+impl Account {
+    fn from_base(base: Option<BaseAccount>, kind: AccountKind) -> Result<Self> {
+        let base = base.stack_err(|| format!("{} without a base account", kind.type_url()))?;
+        Ok(Self {
+            address: base.address,
+            kind,
+            account_number: base.account_number,
+            sequence: base.sequence,
+            module_name: None,
+            vesting: None,
+            balances: Coins::new(),
+        })
+    }
+
+    fn from_vesting(
+        base_vesting: Option<BaseVestingAccount>,
+        kind: AccountKind,
+        start_time: Option<i64>,
+        periods: Vec<VestingPeriod>,
+    ) -> Result<Self> {
+        let base_vesting = base_vesting
+            .stack_err(|| format!("{} without a base vesting account", kind.type_url()))?;
+        let mut account = Self::from_base(base_vesting.base_account, kind).stack()?;
+        account.vesting = Some(Vesting {
+            original_vesting: proto_coins(&base_vesting.original_vesting).stack()?,
+            delegated_free: proto_coins(&base_vesting.delegated_free).stack()?,
+            delegated_vesting: proto_coins(&base_vesting.delegated_vesting).stack()?,
+            start_time,
+            end_time: base_vesting.end_time,
+            periods,
+        });
+        Ok(account)
+    }
+
+    /// Decodes an account as returned by the auth module, without balances.
+    /// Accounts of other types keep their base account if they embed one, and
+    /// are `None` with a warning otherwise.
+    pub fn decode(any: &prost_types::Any) -> Result<Option<Self>> {
+        let kind = AccountKind::from_type_url(&any.type_url);
+        let bytes = any.value.as_slice();
+        let account = match kind {
+            AccountKind::Base => {
+                Self::from_base(Some(BaseAccount::decode(bytes).stack()?), kind).stack()?
+            }
+            AccountKind::Module => {
+                let module = ModuleAccount::decode(bytes).stack()?;
+                let mut account = Self::from_base(module.base_account, kind).stack()?;
+                account.module_name = Some(module.name);
+                account
+            }
+            AccountKind::ContinuousVesting => {
+                let vesting = ContinuousVestingAccount::decode(bytes).stack()?;
+                Self::from_vesting(
+                    vesting.base_vesting_account,
+                    kind,
+                    Some(vesting.start_time),
+                    vec![],
+                )
+                .stack()?
+            }
+            AccountKind::DelayedVesting => {
+                let vesting = DelayedVestingAccount::decode(bytes).stack()?;
+                Self::from_vesting(vesting.base_vesting_account, kind, None, vec![]).stack()?
+            }
+            AccountKind::PeriodicVesting => {
+                let vesting = PeriodicVestingAccount::decode(bytes).stack()?;
+                let mut periods = vec![];
+                for period in vesting.vesting_periods {
+                    periods.push(VestingPeriod {
+                        length: period.length,
+                        amount: proto_coins(&period.amount).stack()?,
+                    });
+                }
+                Self::from_vesting(
+                    vesting.base_vesting_account,
+                    kind,
+                    Some(vesting.start_time),
+                    periods,
+                )
+                .stack()?
+            }
+            AccountKind::PermanentLocked => {
+                let vesting = PermanentLockedAccount::decode(bytes).stack()?;
+                Self::from_vesting(vesting.base_vesting_account, kind, None, vec![]).stack()?
+            }
+            AccountKind::Other(_) => match EmbeddedBaseAccount::decode(bytes) {
+                Ok(EmbeddedBaseAccount {
+                    base_account: Some(base),
+                }) if !base.address.is_empty() => Self::from_base(Some(base), kind).stack()?,
+                _ => {
+                    warn!(
+                        "skipping an account of type {} without a base account",
+                        any.type_url
+                    );
+                    return Ok(None)
+                }
+            },
+        };
+        Ok(Some(account))
+    }
+
+    fn row(&self) -> AccountRow {
+        let vesting = self.vesting.as_ref();
+        let vesting_coins =
+            |f: fn(&Vesting) -> &Coins| vesting.map(f).map_or_else(String::new, coins_to_string);
+        AccountRow {
+            address: &self.address,
+            kind: self.kind.type_url(),
+            account_number: self.account_number,
+            sequence: self.sequence,
+            module_name: self.module_name.as_deref().unwrap_or_default(),
+            balances: coins_to_string(&self.balances),
+            original_vesting: vesting_coins(|vesting| &vesting.original_vesting),
+            delegated_free: vesting_coins(|vesting| &vesting.delegated_free),
+            delegated_vesting: vesting_coins(|vesting| &vesting.delegated_vesting),
+            start_time: vesting.and_then(|vesting| vesting.start_time),
+            end_time: vesting.map(|vesting| vesting.end_time),
+        }
+    }
+}
+
+impl Account {
+    /// Parses an account of `app_state.auth.accounts` of a genesis file,
+    /// without balances. Accounts of other types keep their base account if
+    /// they embed one, and are `None` with a warning otherwise.
+    pub fn from_genesis(value: &Value) -> Result<Option<Self>> {
+        let type_url = account_type(value).stack_err(|| "account without a @type")?;
+        let kind = AccountKind::from_type_url(type_url);
+        let base = match kind {
+            AccountKind::Base => value,
+            AccountKind::Module => json_field(value, "/base_account").stack()?,
+            AccountKind::Other(_) => match value.get("base_account") {
+                Some(base) => base,
+                None => {
+                    warn!("skipping an account of type {type_url} without a base account");
+                    return Ok(None)
+                }
+            },
+            _ => json_field(value, "/base_vesting_account/base_account").stack()?,
+        };
+        let is_vesting = !matches!(
+            kind,
+            AccountKind::Base | AccountKind::Module | AccountKind::Other(_)
+        );
+        let mut account = Self {
+            address: json_field(base, "/address")
+                .stack()?
+                .as_str()
+                .stack()?
+                .to_owned(),
+            kind: kind.clone(),
+            account_number: json_int(json_field(base, "/account_number").stack()?).stack()?,
+            sequence: json_int(json_field(base, "/sequence").stack()?).stack()?,
+            module_name: None,
+            vesting: None,
+            balances: Coins::new(),
+        };
+        if kind == AccountKind::Module {
+            account.module_name = Some(
+                json_field(value, "/name")
+                    .stack()?
+                    .as_str()
+                    .stack()?
+                    .to_owned(),
+            );
+        } else if is_vesting {
+            let base_vesting = json_field(value, "/base_vesting_account").stack()?;
+            let coins = |pointer: &str| -> Result<Coins> {
+                // empty coins are sometimes omitted
+                base_vesting
+                    .pointer(pointer)
+                    .map_or(Ok(Coins::new()), parse_coins)
+            };
+            let mut periods = vec![];
+            if let Some(vesting_periods) = value.get("vesting_periods") {
+                for period in vesting_periods.as_array().stack()? {
+                    periods.push(VestingPeriod {
+                        length: json_int(json_field(period, "/length").stack()?).stack()?,
+                        amount: parse_coins(json_field(period, "/amount").stack()?).stack()?,
+                    });
+                }
+            }
+            account.vesting = Some(Vesting {
+                original_vesting: coins("/original_vesting").stack()?,
+                delegated_free: coins("/delegated_free").stack()?,
+                delegated_vesting: coins("/delegated_vesting").stack()?,
+                start_time: match value.get("start_time") {
+                    Some(start_time) => Some(json_int(start_time).stack()?),
+                    None => None,
+                },
+                end_time: json_int(json_field(base_vesting, "/end_time").stack()?).stack()?,
+                periods,
+            });
+        }
+        Ok(Some(account))
+    }
+
+    /// The coins that have vested at `time` in UNIX seconds, with the same
+    /// math as `GetVestedCoins` of the SDK vesting accounts. This is empty for
+    /// accounts that are not vesting.
+    pub fn vested_coins(&self, time: i64) -> Coins {
+        let vesting = match self.vesting {
+            Some(ref vesting) => vesting,
+            None => return Coins::new(),
+        };
+        let start_time = vesting.start_time.unwrap_or_default();
+        match self.kind {
+            AccountKind::ContinuousVesting | AccountKind::PeriodicVesting if time <= start_time => {
+                Coins::new()
+            }
+            AccountKind::PermanentLocked => Coins::new(),
+            _ if time >= vesting.end_time => vesting.original_vesting.clone(),
+            AccountKind::ContinuousVesting => {
+                let elapsed = dec_quo(
+                    (time - start_time) as u128,
+                    (vesting.end_time - start_time) as u128,
+                );
+                vesting
+                    .original_vesting
+                    .iter()
+                    .map(|(denom, amount)| (denom.clone(), dec_mul_round_int(*amount, elapsed)))
+                    .collect()
+            }
+            AccountKind::PeriodicVesting => {
+                let mut vested = Coins::new();
+                let mut period_start = start_time;
+                for period in &vesting.periods {
+                    if time - period_start < period.length {
+                        break
+                    }
+                    add_coins(&mut vested, &period.amount);
+                    period_start += period.length;
+                }
+                vested
+            }
+            // delayed vesting before the end time
+            _ => Coins::new(),
+        }
+    }
+
+    /// The original vesting minus the vested coins at `time`, like
+    /// `GetVestingCoins` of the SDK
+    pub fn vesting_coins(&self, time: i64) -> Coins {
+        match self.vesting {
+            Some(ref vesting) => {
+                saturating_sub_coins(&vesting.original_vesting, &self.vested_coins(time))
+            }
+            None => Coins::new(),
+        }
+    }
+
+    /// The time in UNIX seconds and the coins of the next unlock after `time`.
+    /// Continuous vesting unlocks every block, for it this is the end time and
+    /// all the coins still vesting.
+    pub fn next_unlock(&self, time: i64) -> Option<(i64, Coins)> {
+        let vesting = self.vesting.as_ref()?;
+        if time >= vesting.end_time {
+            return None
+        }
+        match self.kind {
+            AccountKind::PeriodicVesting => {
+                let mut period_end = vesting.start_time.unwrap_or_default();
+                for period in &vesting.periods {
+                    period_end += period.length;
+                    if period_end > time {
+                        return Some((period_end, period.amount.clone()))
+                    }
+                }
+                // anything not covered by the periods vests at the end time
+                Some((vesting.end_time, self.vesting_coins(time)))
+                    .filter(|(_, coins)| !coins.is_empty())
+            }
+            AccountKind::ContinuousVesting | AccountKind::DelayedVesting => {
+                Some((vesting.end_time, self.vesting_coins(time)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The vesting state of the account at `time` in UNIX seconds, or `None`
+    /// if it is not a vesting account
+    pub fn vesting_status(&self, time: i64) -> Option<VestingStatus> {
+        let vesting = self.vesting.as_ref()?;
+        let unvested = self.vesting_coins(time);
+        // `LockedCoins`, delegated vesting coins are locked by staking instead
+        let locked = saturating_sub_coins(&unvested, &vesting.delegated_vesting);
+        // `SpendableCoins` is empty if any denom of the balance is below the
+        // locked amount
+        let spendable = if locked
+            .iter()
+            .any(|(denom, amount)| self.balances.get(denom).copied().unwrap_or_default() < *amount)
+        {
+            Coins::new()
+        } else {
+            saturating_sub_coins(&self.balances, &locked)
+        };
+        Some(VestingStatus {
+            vested: self.vested_coins(time),
+            unvested,
+            delegated_free: vesting.delegated_free.clone(),
+            delegated_vesting: vesting.delegated_vesting.clone(),
+            locked,
+            spendable,
+            next_unlock: self.next_unlock(time),
+        })
+    }
+}
+
+/// The state of a vesting account at a point in time, with the same math as
+/// the SDK vesting accounts and bank keeper
+#[derive(Debug, Clone)]
+pub struct VestingStatus {
+    /// `GetVestedCoins`
+    pub vested: Coins,
+    /// `GetVestingCoins`
+    pub unvested: Coins,
+    /// The SDK tracks the delegated amounts when delegating and undelegating,
+    /// so these are the stored amounts and do not change with time
+    pub delegated_free: Coins,
+    pub delegated_vesting: Coins,
+    /// The unvested coins that are not delegated and cannot be sent
+    pub locked: Coins,
+    /// The balance that can be sent
+    pub spendable: Coins,
+    /// The UNIX time in seconds and coins of the next unlock
+    pub next_unlock: Option<(i64, Coins)>,
+}
+
+/// Queries a single account and its balances at the latest height
+pub async fn query_account(grpc: &str, address: &str) -> Result<Account> {
+    let mut client = AuthQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let response = client
+        .account(QueryAccountRequest {
+            address: address.to_owned(),
+        })
+        .await
+        .stack_err(|| format!("could not query the account {address} on {grpc}"))?;
+    let height = response_height(response.metadata())
+        .stack_err(|| format!("{grpc} did not return the {BLOCK_HEIGHT_HEADER} header"))?;
+    let any = response
+        .into_inner()
+        .account
+        .stack_err(|| format!("{address} has no account"))?;
+    let mut account = Account::decode(&any)
+        .stack()?
+        .stack_err(|| format!("{address} has an unsupported account type {}", any.type_url))?;
+    let bank_client = BankQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    account.balances = query_all_balances(bank_client, address, height)
+        .await
+        .stack()?;
+    Ok(account)
+}
+
+/// Queries every account of the auth module at `height`, or at the latest
+/// height which is then used for every page. Returns the height and the
+/// accounts without balances.
+pub async fn query_accounts(grpc: &str, height: Option<u64>) -> Result<(u64, Vec<Account>)> {
+    let mut client = AuthQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut height = height;
+    let mut accounts = vec![];
+    let mut next_key = vec![];
+    loop {
+        let response = client
+            .accounts(
+                request_at(
+                    QueryAccountsRequest {
+                        pagination: page_request(next_key),
+                    },
+                    height,
+                )
+                .stack()?,
+            )
+            .await
+            .stack_err(|| format!("could not query the accounts on {grpc}"))?;
+        if height.is_none() {
+            // pin the pages that follow to the height of the first one
+            height =
+                Some(response_height(response.metadata()).stack_err(|| {
+                    format!("{grpc} did not return the {BLOCK_HEIGHT_HEADER} header")
+                })?);
+        }
+        let response = response.into_inner();
+        for any in &response.accounts {
+            if let Some(account) = Account::decode(any).stack()? {
+                accounts.push(account);
+            }
+        }
+        next_key = response
+            .pagination
+            .map(|page| page.next_key)
+            .unwrap_or_default();
+        if next_key.is_empty() {
+            break
+        }
+    }
+    // `height` is always set after the first page
+    Ok((height.unwrap_or_default(), accounts))
+}
+
+async fn query_all_balances(
+    mut client: BankQueryClient<Channel>,
+    address: &str,
+    height: u64,
+) -> Result<Coins> {
+    let mut balances = Coins::new();
+    let mut next_key = vec![];
+    loop {
+        let response = client
+            .all_balances(
+                request_at(
+                    QueryAllBalancesRequest {
+                        address: address.to_owned(),
+                        pagination: page_request(next_key),
+                    },
+                    Some(height),
+                )
+                .stack()?,
+            )
+            .await
+            .stack_err(|| format!("could not query the balances of {address}"))?
+            .into_inner();
+        balances.extend(proto_coins(&response.balances).stack()?);
+        next_key = response
+            .pagination
+            .map(|page| page.next_key)
+            .unwrap_or_default();
+        if next_key.is_empty() {
+            break
+        }
+    }
+    Ok(balances)
+}
+
+/// Fills in the bank balances of `accounts` at `height`, with up to
+/// `concurrency` queries in flight
+pub async fn query_balances(
+    grpc: &str,
+    accounts: &mut [Account],
+    height: u64,
+    concurrency: usize,
+) -> Result<()> {
+    let client = BankQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let balances: Vec<Coins> = stream::iter(accounts.iter())
+        .map(|account| query_all_balances(client.clone(), &account.address, height))
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+        .stack()?;
+    for (account, balances) in accounts.iter_mut().zip(balances) {
+        account.balances = balances;
+    }
+    Ok(())
+}
+
+impl AccountExport {
+    /// Queries every account and its balances at `height`, or the latest
+    /// height
+    pub async fn query(
+        grpc: &str,
+        chain_id: &str,
+        height: Option<u64>,
+        concurrency: usize,
+    ) -> Result<Self> {
+        let (height, mut accounts) = query_accounts(grpc, height).await.stack()?;
+        query_balances(grpc, &mut accounts, height, concurrency)
+            .await
+            .stack()?;
+        Ok(Self {
+            chain_id: chain_id.to_owned(),
+            height,
+            accounts,
+        })
+    }
+
+    pub async fn load(path: &str) -> Result<Self> {
+        let s = FileOptions::read_to_string(path).await.stack()?;
+        serde_json::from_str(&s).stack_err(|| format!("failed to parse account export {path}"))
+    }
+
+    /// Collects the accounts of a genesis file with their balances from
+    /// `app_state.bank.balances`, at the `initial_height` of the genesis.
+    /// Balances of addresses without an account are left out with a warning,
+    /// so the totals of the export can be below the bank supply.
+    pub fn from_genesis(genesis: &Value) -> Result<Self> {
+        let mut balances = BTreeMap::<&str, Coins>::new();
+        for balance in json_field(genesis, "/app_state/bank/balances")
+            .stack()?
+            .as_array()
+            .stack()?
+        {
+            balances.insert(
+                json_field(balance, "/address").stack()?.as_str().stack()?,
+                parse_coins(json_field(balance, "/coins").stack()?).stack()?,
+            );
+        }
+        let mut accounts = vec![];
+        for value in json_field(genesis, "/app_state/auth/accounts")
+            .stack()?
+            .as_array()
+            .stack()?
+        {
+            let mut account = match Account::from_genesis(value).stack()? {
+                Some(account) => account,
+                None => continue,
+            };
+            account.balances = balances
+                .remove(account.address.as_str())
+                .unwrap_or_default();
+            accounts.push(account);
+        }
+        if !balances.is_empty() {
+            let mut leftover = Coins::new();
+            for coins in balances.values() {
+                add_coins(&mut leftover, coins);
+            }
+            warn!(
+                "{} bank balances have no auth account and are not in the export, totaling {}",
+                balances.len(),
+                coins_to_string(&leftover)
+            );
+        }
+        Ok(Self {
+            chain_id: genesis
+                .get("chain_id")
+                .and_then(|chain_id| chain_id.as_str())
+                .unwrap_or_default()
+                .to_owned(),
+            height: genesis
+                .get("initial_height")
+                .map_or(Ok(0), json_int)
+                .stack()?,
+            accounts,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).stack()
+    }
+
+    /// One row per address
+    pub fn to_csv(&self) -> Result<String> {
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        for account in &self.accounts {
+            wtr.serialize(account.row()).stack()?;
+        }
+        String::from_utf8(wtr.into_inner().stack()?).stack()
+    }
+
+    /// The total balances of all accounts
+    pub fn total_balances(&self) -> Result<Coins> {
+        let mut total = Coins::new();
+        for account in &self.accounts {
+            for (denom, amount) in &account.balances {
+                let entry = total.entry(denom.clone()).or_default();
+                *entry = entry
+                    .checked_add(*amount)
+                    .stack_err(|| "balance overflow")?;
+            }
+        }
+        Ok(total)
+    }
+}
+
+/// `continuous_vesting` and `periodic_vesting` use the vectors of the
+/// `TestGetVestedCoins*` tests of the SDK in
+/// `x/auth/vesting/types/vesting_account_test.go`, the other tests cover the
+/// rounding and delegation edge cases of the same math
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const HOUR: i64 = 60 * 60;
+
+    fn coins(coins: &[(&str, u128)]) -> Coins {
+        coins
+            .iter()
+            .map(|(denom, amount)| (denom.to_string(), *amount))
+            .collect()
+    }
+
+    fn vesting_account(
+        kind: AccountKind,
+        original_vesting: Coins,
+        end_time: i64,
+        periods: Vec<VestingPeriod>,
+    ) -> Account {
+        Account {
+            address: "onomy1vesting".to_owned(),
+            kind,
+            account_number: 0,
+            sequence: 0,
+            module_name: None,
+            vesting: Some(Vesting {
+                original_vesting: original_vesting.clone(),
+                delegated_free: Coins::new(),
+                delegated_vesting: Coins::new(),
+                start_time: Some(NOW),
+                end_time,
+                periods,
+            }),
+            balances: original_vesting,
+        }
+    }
+
+    #[test]
+    fn continuous_vesting() {
+        let account = vesting_account(
+            AccountKind::ContinuousVesting,
+            coins(&[("fee", 1000), ("stake", 100)]),
+            NOW + (24 * HOUR),
+            vec![],
+        );
+        assert_eq!(account.vested_coins(NOW), Coins::new());
+        assert_eq!(
+            account.vested_coins(NOW + (12 * HOUR)),
+            coins(&[("fee", 500), ("stake", 50)])
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (24 * HOUR)),
+            coins(&[("fee", 1000), ("stake", 100)])
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (48 * HOUR)),
+            coins(&[("fee", 1000), ("stake", 100)])
+        );
+    }
+
+    #[test]
+    fn continuous_vesting_rounds_half_to_even() {
+        // `RoundInt` of `sdk.Dec` rounds 500.5 down and 501.5 up
+        let account = vesting_account(
+            AccountKind::ContinuousVesting,
+            coins(&[("fee", 1001), ("stake", 1003)]),
+            NOW + (24 * HOUR),
+            vec![],
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (12 * HOUR)),
+            coins(&[("fee", 500), ("stake", 502)])
+        );
+        assert_eq!(
+            account.vesting_coins(NOW + (12 * HOUR)),
+            coins(&[("fee", 501), ("stake", 501)])
+        );
+    }
+
+    #[test]
+    fn periodic_vesting() {
+        let period = |length, fee, stake| VestingPeriod {
+            length,
+            amount: coins(&[("fee", fee), ("stake", stake)]),
+        };
+        let account = vesting_account(
+            AccountKind::PeriodicVesting,
+            coins(&[("fee", 1000), ("stake", 100)]),
+            NOW + (24 * HOUR),
+            vec![
+                period(12 * HOUR, 500, 50),
+                period(6 * HOUR, 250, 25),
+                period(6 * HOUR, 250, 25),
+            ],
+        );
+        assert_eq!(account.vested_coins(NOW), Coins::new());
+        assert_eq!(account.vested_coins(NOW + (12 * HOUR) - 1), Coins::new());
+        assert_eq!(
+            account.vested_coins(NOW + (12 * HOUR)),
+            coins(&[("fee", 500), ("stake", 50)])
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (18 * HOUR) - 1),
+            coins(&[("fee", 500), ("stake", 50)])
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (18 * HOUR)),
+            coins(&[("fee", 750), ("stake", 75)])
+        );
+        assert_eq!(
+            account.vested_coins(NOW + (24 * HOUR)),
+            coins(&[("fee", 1000), ("stake", 100)])
+        );
+        assert_eq!(
+            account.next_unlock(NOW + (12 * HOUR)),
+            Some((NOW + (18 * HOUR), coins(&[("fee", 250), ("stake", 25)])))
+        );
+    }
+
+    #[test]
+    fn delegated_vesting_above_unvested() {
+        // all the stake was delegated at the start, the delegated vesting
+        // amount is only updated on undelegation so it stays above the
+        // unvested amount
+        let mut account = vesting_account(
+            AccountKind::ContinuousVesting,
+            coins(&[("fee", 1000), ("stake", 100)]),
+            NOW + (24 * HOUR),
+            vec![],
+        );
+        account.vesting.as_mut().unwrap().delegated_vesting = coins(&[("stake", 100)]);
+        account.balances = coins(&[("fee", 1000)]);
+
+        let status = account.vesting_status(NOW + (12 * HOUR)).unwrap();
+        assert_eq!(status.unvested, coins(&[("fee", 500), ("stake", 50)]));
+        assert_eq!(status.locked, coins(&[("fee", 500)]));
+        assert_eq!(status.spendable, coins(&[("fee", 500)]));
+    }
+}
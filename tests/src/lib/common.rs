@@ -6,10 +6,30 @@ use onomy_test_lib::{
     },
     Args, TIMEOUT,
 };
+pub mod accounts;
 pub mod contest;
+pub mod endpoint_health;
+pub mod genesis;
 mod get_key;
+pub mod health;
+pub mod hermes_config;
+pub mod hermes_log;
+pub mod ibc;
+pub mod ics;
+pub mod preflight;
+pub mod profiles;
+pub mod recipe;
+pub mod relayer_config;
+pub mod sybil;
+pub mod validators;
 pub use get_key::*;
 
+/// ANSI colors for the reports that the binaries print
+pub const RED: &str = "\x1b[31m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const GREEN: &str = "\x1b[32m";
+pub const RESET: &str = "\x1b[0m";
+
 /// Useful for running simple container networks that have a standard format and
 /// don't need extra build or volume arguments.
 pub async fn container_runner(args: &Args, name_and_contents: &[(&str, &str)]) -> Result<()> {
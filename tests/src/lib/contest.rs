@@ -1,14 +1,18 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use cosmos_sdk_proto::cosmos::bank::v1beta1::MsgSend;
 use deep_space::{u256, Coin, Msg, PrivateKey};
 use onomy_test_lib::{
     reprefix_bech32,
-    super_orchestrator::stacked_errors::{ensure_eq, Result, StackableErr},
+    super_orchestrator::stacked_errors::{ensure_eq, Error, Result, StackableErr},
 };
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawRecord {
-    // could be parsed with `chrono` if needed
+    // parsed with a `TimestampFormat`
     pub timestamp: String,
     pub discord_user: String,
     pub addr: String,
@@ -16,32 +20,177 @@ pub struct RawRecord {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    pub discord_user: String,
+    pub addr: String,
+}
+
+/// Accepts RFC 3339 times and the legacy `TimestampFormat::default()` strings
+/// of whitelists written before `Record::timestamp` was parsed
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<DateTime<Utc>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    TimestampFormat::default()
+        .parse(&s)
+        .map_err(|e| de::Error::custom(format!("{e:?}")))
+}
+
+/// How to parse `RawRecord::timestamp`s
+#[derive(Debug, Clone)]
+pub struct TimestampFormat {
+    /// The `chrono` format string
+    pub format: String,
+    /// The timezone that the timestamps are local to
+    pub timezone: Tz,
+}
+
+impl Default for TimestampFormat {
+    /// Google Forms style `M/D/YYYY H:MM:SS` in UTC
+    fn default() -> Self {
+        Self {
+            format: "%m/%d/%Y %H:%M:%S".to_owned(),
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl TimestampFormat {
+    /// `timezone` should be an IANA name such as "US/Central"
+    pub fn new(format: &str, timezone: &str) -> Result<Self> {
+        Ok(Self {
+            format: format.to_owned(),
+            timezone: Tz::from_str(timezone).map_err(Error::from).stack()?,
+        })
+    }
+
+    /// Parses an RFC 3339 time or a local time in this format
+    pub fn parse(&self, s: &str) -> Result<DateTime<Utc>> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(time.with_timezone(&Utc))
+        }
+        let naive = NaiveDateTime::parse_from_str(s, &self.format)
+            .stack_err(|| format!("could not parse timestamp \"{s}\""))?;
+        // the earliest is chosen for ambiguous times around DST changes
+        let local = self
+            .timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .stack_err(|| format!("timestamp \"{s}\" does not exist in {}", self.timezone))?;
+        Ok(local.with_timezone(&Utc))
+    }
+}
+
+/// The reason a `RawRecord` did not make it into the whitelist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    InvalidTimestamp(String),
+    /// Submitted before the window opened
+    TooEarly,
+    /// Submitted after the window closed
+    TooLate,
+    InvalidBech32(String),
+    WrongDataLength(usize),
+    Bech32m,
+    NonAccountPrefix(String),
+    /// Duplicate of the address in the given row, which was retained
+    Duplicate {
+        winning_row: usize,
+    },
+    ModuleAccount,
+    /// Removed by the sybil policy, the given row was retained for the user
+    SybilPolicy {
+        kept_row: usize,
+    },
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::InvalidTimestamp(s) => write!(f, "invalid timestamp \"{s}\""),
+            RejectReason::TooEarly => write!(f, "submitted before the window opened"),
+            RejectReason::TooLate => write!(f, "submitted after the window closed"),
+            RejectReason::InvalidBech32(e) => write!(f, "invalid bech32 ({e})"),
+            RejectReason::WrongDataLength(len) => {
+                write!(f, "wrong data length ({len} 5-bit groups, expected 32)")
+            }
+            RejectReason::Bech32m => write!(f, "bech32m variant"),
+            RejectReason::NonAccountPrefix(prefix) => {
+                write!(f, "non-account prefix \"{prefix}\"")
+            }
+            RejectReason::Duplicate { winning_row } => {
+                write!(f, "duplicate address (row {winning_row} was retained)")
+            }
+            RejectReason::ModuleAccount => write!(f, "module account"),
+            RejectReason::SybilPolicy { kept_row } => {
+                write!(
+                    f,
+                    "another address of the same user (row {kept_row}) was retained"
+                )
+            }
+        }
+    }
+}
+
+/// A row of the rejection report written by `process_contest_whitelist.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rejection {
+    /// The row in the original csv, counting the header as row 1
+    pub row: usize,
     pub timestamp: String,
     pub discord_user: String,
     pub addr: String,
+    pub reason: String,
+}
+
+impl Rejection {
+    pub fn new(row: usize, raw: &RawRecord, reason: &RejectReason) -> Self {
+        Self {
+            row,
+            timestamp: raw.timestamp.clone(),
+            discord_user: raw.discord_user.clone(),
+            addr: raw.addr.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Returns if the bech32 human readable part is for something other than a
+/// normal account, e.x. `onomyvaloper` or `cosmosvalconspub`
+fn is_non_account_prefix(prefix: &str) -> bool {
+    ["valoper", "valcons", "pub"]
+        .iter()
+        .any(|suffix| prefix.ends_with(suffix))
 }
 
 impl Record {
-    pub fn from_raw_record(raw: &RawRecord) -> Option<Self> {
+    pub fn from_raw_record(
+        raw: &RawRecord,
+        timestamp_format: &TimestampFormat,
+    ) -> std::result::Result<Self, RejectReason> {
         use bech32::Variant;
-        match bech32::decode(&raw.addr) {
-            Ok((_, data, variant)) => {
-                if (variant == Variant::Bech32) && (data.len() == 32) {
-                    // reprefix for some people
-                    let reprefixed = reprefix_bech32(&raw.addr, "onomy").unwrap();
-                    return Some(Self {
-                        timestamp: raw.timestamp.clone(),
-                        discord_user: raw.discord_user.clone(),
-                        addr: reprefixed,
-                    })
-                }
-                //dbg!(prefix, data.len(), variant);
-            }
-            Err(_) => {
-                //dbg!(&raw.addr);
-            }
+        let timestamp = timestamp_format
+            .parse(&raw.timestamp)
+            .map_err(|_| RejectReason::InvalidTimestamp(raw.timestamp.clone()))?;
+        let (prefix, data, variant) =
+            bech32::decode(&raw.addr).map_err(|e| RejectReason::InvalidBech32(e.to_string()))?;
+        if variant != Variant::Bech32 {
+            return Err(RejectReason::Bech32m)
+        }
+        if data.len() != 32 {
+            return Err(RejectReason::WrongDataLength(data.len()))
+        }
+        if is_non_account_prefix(&prefix) {
+            return Err(RejectReason::NonAccountPrefix(prefix))
         }
-        None
+        // reprefix for some people
+        let reprefixed = reprefix_bech32(&raw.addr, "onomy").unwrap();
+        Ok(Self {
+            timestamp,
+            discord_user: raw.discord_user.clone(),
+            addr: reprefixed,
+        })
     }
 
     pub fn verify(&self, prefix: &str) -> Result<()> {
@@ -54,6 +203,41 @@ impl Record {
     }
 }
 
+/// Prices for valuing contest portfolios
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceTable {
+    /// The price of one whole token, keyed by base denom
+    pub prices: BTreeMap<String, f64>,
+    /// Decimals of the base denoms, 18 if not present
+    #[serde(default)]
+    pub decimals: BTreeMap<String, u32>,
+}
+
+impl PriceTable {
+    /// Returns the total value of `balances`, denoms without a price are valued
+    /// at 0
+    pub fn value(&self, balances: &BTreeMap<String, u128>) -> f64 {
+        let mut total = 0.0;
+        for (denom, amount) in balances {
+            if let Some(price) = self.prices.get(denom) {
+                let decimals = self.decimals.get(denom).copied().unwrap_or(18);
+                total += ((*amount as f64) / 10f64.powi(decimals as i32)) * price;
+            }
+        }
+        total
+    }
+}
+
+/// A row of the leaderboard written by `contest_results.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardRow {
+    pub rank: usize,
+    pub discord_user: String,
+    pub addr: String,
+    pub value: f64,
+    pub balances: String,
+}
+
 pub fn get_txs(private_key: PrivateKey, records: &[Record]) -> Result<Vec<Msg>> {
     let from_address = private_key
         .to_address("onomy")
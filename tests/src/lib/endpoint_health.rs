@@ -0,0 +1,367 @@
+//! Latest block checks over the gRPC, CometBFT RPC, and REST endpoints of
+//! nodes, used by `grpc_health`
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use deep_space::{client::types::LatestBlock, Contact};
+use futures::future::join_all;
+use onomy_test_lib::super_orchestrator::stacked_errors::{Error, Result, StackableErr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::relayer_config::Network;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointKind {
+    Grpc,
+    Rpc,
+    Rest,
+}
+
+impl fmt::Display for EndpointKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Grpc => "gRPC",
+            Self::Rpc => "RPC",
+            Self::Rest => "REST",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub kind: EndpointKind,
+    pub url: String,
+    /// The address prefix that `Contact` needs for gRPC endpoints
+    pub prefix: String,
+}
+
+impl Endpoint {
+    pub fn new(kind: EndpointKind, url: &str, prefix: &str) -> Self {
+        Self {
+            kind,
+            url: url.trim_end_matches('/').to_owned(),
+            prefix: prefix.to_owned(),
+        }
+    }
+}
+
+/// The gRPC, RPC, and REST endpoints of every chain in the network
+pub fn network_endpoints(network: &Network) -> Vec<Endpoint> {
+    let mut endpoints = vec![];
+    for chain in &network.chains {
+        endpoints.push(Endpoint::new(
+            EndpointKind::Grpc,
+            &chain.grpc_addr(),
+            &chain.prefix,
+        ));
+        endpoints.push(Endpoint::new(
+            EndpointKind::Rpc,
+            &chain.rpc_addr(),
+            &chain.prefix,
+        ));
+        endpoints.push(Endpoint::new(
+            EndpointKind::Rest,
+            &chain.rest_addr(),
+            &chain.prefix,
+        ));
+    }
+    endpoints
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct LatestInfo {
+    chain_id: Option<String>,
+    height: Option<u64>,
+    block_time: Option<DateTime<Utc>>,
+    catching_up: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub kind: EndpointKind,
+    pub url: String,
+    pub checked_at: DateTime<Utc>,
+    pub latency_ms: u64,
+    pub chain_id: Option<String>,
+    /// `None` if the check failed or the node is waiting for genesis
+    pub height: Option<u64>,
+    pub block_time: Option<DateTime<Utc>>,
+    /// `None` for REST, which does not report it
+    pub catching_up: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl EndpointStatus {
+    pub fn block_age(&self) -> Option<chrono::Duration> {
+        self.block_time
+            .map(|block_time| self.checked_at - block_time)
+    }
+
+    /// Why this endpoint is unhealthy, `best_height` is the highest height seen
+    /// for the chain ID of this endpoint
+    pub fn problem(&self, best_height: Option<u64>, max_lag: u64) -> Option<String> {
+        let name = format!("{} {}", self.kind, self.url);
+        if let Some(ref error) = self.error {
+            return Some(format!("{name}: {error}"))
+        }
+        if self.catching_up == Some(true) {
+            return Some(format!("{name} is catching up"))
+        }
+        let height = match self.height {
+            Some(height) => height,
+            None => return Some(format!("{name} has no blocks yet")),
+        };
+        let lag = best_height.unwrap_or(height).saturating_sub(height);
+        if lag > max_lag {
+            return Some(format!(
+                "{name} is {lag} blocks behind, more than the maximum of {max_lag}"
+            ))
+        }
+        None
+    }
+}
+
+/// Statistics over the history of one endpoint
+#[derive(Debug, Clone)]
+pub struct WindowStats {
+    pub checks: usize,
+    /// Checks that returned a block, regardless of lag
+    pub up: usize,
+    /// Average seconds per block between the first and last block seen
+    pub avg_block_time: Option<f64>,
+    /// The number of times the height stopped advancing between consecutive
+    /// successful checks
+    pub stalls: usize,
+    /// The longest time that the height did not advance
+    pub longest_stall: chrono::Duration,
+}
+
+impl WindowStats {
+    pub fn uptime_percent(&self) -> f64 {
+        if self.checks == 0 {
+            0.0
+        } else {
+            (self.up as f64) * 100.0 / (self.checks as f64)
+        }
+    }
+
+    /// Computes the stats of `history`, which should be the statuses of one
+    /// endpoint sorted by `checked_at`
+    pub fn new(history: &[EndpointStatus]) -> Self {
+        let mut stats = Self {
+            checks: history.len(),
+            up: 0,
+            avg_block_time: None,
+            stalls: 0,
+            longest_stall: chrono::Duration::zero(),
+        };
+        let up: Vec<(&EndpointStatus, u64)> = history
+            .iter()
+            .filter(|status| status.error.is_none())
+            .filter_map(|status| Some((status, status.height?)))
+            .collect();
+        stats.up = up.len();
+
+        let blocks: Vec<_> = up
+            .iter()
+            .filter_map(|(status, height)| Some((*height, status.block_time?)))
+            .collect();
+        if let (Some(first), Some(last)) = (blocks.first(), blocks.last()) {
+            if last.0 > first.0 {
+                stats.avg_block_time = Some(
+                    (last.1 - first.1).num_milliseconds() as f64
+                        / 1000.0
+                        / ((last.0 - first.0) as f64),
+                );
+            }
+        }
+
+        // the start of the current run of checks without height progress
+        let mut stall_start: Option<DateTime<Utc>> = None;
+        for pair in up.windows(2) {
+            let ((prev, prev_height), (next, next_height)) = (pair[0], pair[1]);
+            if next_height <= prev_height {
+                let start = *stall_start.get_or_insert_with(|| {
+                    stats.stalls += 1;
+                    prev.checked_at
+                });
+                stats.longest_stall = stats.longest_stall.max(next.checked_at - start);
+            } else {
+                stall_start = None;
+            }
+        }
+        stats
+    }
+}
+
+/// The highest height seen for each chain ID
+pub fn best_heights(statuses: &[EndpointStatus]) -> BTreeMap<String, u64> {
+    let mut best = BTreeMap::new();
+    for status in statuses {
+        if let (Some(chain_id), Some(height)) = (&status.chain_id, status.height) {
+            let entry = best.entry(chain_id.clone()).or_insert(height);
+            *entry = (*entry).max(height);
+        }
+    }
+    best
+}
+
+fn json_str<'a>(value: &'a Value, path: &[&str]) -> Result<&'a str> {
+    let mut value = value;
+    for key in path {
+        value = value
+            .get(key)
+            .stack_err(|| format!("response is missing `{}`", path.join(".")))?;
+    }
+    value
+        .as_str()
+        .stack_err(|| format!("`{}` is not a string", path.join(".")))
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(s)
+        .stack_err(|| format!("could not parse block time {s}"))?
+        .with_timezone(&Utc))
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .stack_err(|| format!("GET {url} failed"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::from(format!("GET {url} returned {status}")))
+    }
+    response
+        .json()
+        .await
+        .stack_err(|| format!("GET {url} did not return JSON"))
+}
+
+async fn check_grpc(endpoint: &Endpoint, timeout: Duration) -> Result<LatestInfo> {
+    let contact = Contact::new(&endpoint.url, timeout, &endpoint.prefix).stack()?;
+    let (block, catching_up) = match contact.get_latest_block().await.stack()? {
+        LatestBlock::Latest { block } => (Some(block), false),
+        LatestBlock::Syncing { block } => (Some(block), true),
+        LatestBlock::WaitingToStart => (None, true),
+    };
+    let mut info = LatestInfo {
+        catching_up: Some(catching_up),
+        ..Default::default()
+    };
+    if let Some(header) = block.and_then(|block| block.header) {
+        info.chain_id = Some(header.chain_id);
+        info.height = Some(u64::try_from(header.height).stack()?);
+        info.block_time = header.time.and_then(|time| {
+            Utc.timestamp_opt(time.seconds, u32::try_from(time.nanos).ok()?)
+                .single()
+        });
+    }
+    Ok(info)
+}
+
+async fn check_rpc(client: &reqwest::Client, endpoint: &Endpoint) -> Result<LatestInfo> {
+    let response = get_json(client, &format!("{}/status", endpoint.url))
+        .await
+        .stack()?;
+    // older versions of CometBFT wrap the response in JSON-RPC
+    let status = response.get("result").unwrap_or(&response);
+    let catching_up = status
+        .get("sync_info")
+        .and_then(|sync_info| sync_info.get("catching_up"))
+        .and_then(|catching_up| catching_up.as_bool());
+    let height: u64 = json_str(status, &["sync_info", "latest_block_height"])?
+        .parse()
+        .stack()?;
+    Ok(LatestInfo {
+        chain_id: Some(json_str(status, &["node_info", "network"])?.to_owned()),
+        // a node waiting for genesis reports a height of 0
+        height: if height == 0 { None } else { Some(height) },
+        block_time: Some(parse_time(json_str(status, &[
+            "sync_info",
+            "latest_block_time",
+        ])?)?),
+        catching_up,
+    })
+}
+
+async fn check_rest(client: &reqwest::Client, endpoint: &Endpoint) -> Result<LatestInfo> {
+    let response = get_json(
+        client,
+        &format!(
+            "{}/cosmos/base/tendermint/v1beta1/blocks/latest",
+            endpoint.url
+        ),
+    )
+    .await
+    .stack()?;
+    Ok(LatestInfo {
+        chain_id: Some(json_str(&response, &["block", "header", "chain_id"])?.to_owned()),
+        height: Some(
+            json_str(&response, &["block", "header", "height"])?
+                .parse()
+                .stack()?,
+        ),
+        block_time: Some(parse_time(json_str(&response, &[
+            "block", "header", "time",
+        ])?)?),
+        catching_up: None,
+    })
+}
+
+pub async fn check_endpoint(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    timeout: Duration,
+) -> EndpointStatus {
+    let checked_at = Utc::now();
+    let start = Instant::now();
+    let res = match endpoint.kind {
+        EndpointKind::Grpc => check_grpc(endpoint, timeout).await,
+        EndpointKind::Rpc => check_rpc(client, endpoint).await,
+        EndpointKind::Rest => check_rest(client, endpoint).await,
+    };
+    let latency_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let (info, error) = match res {
+        Ok(info) => (info, None),
+        Err(e) => (LatestInfo::default(), Some(format!("{e:?}"))),
+    };
+    EndpointStatus {
+        kind: endpoint.kind,
+        url: endpoint.url.clone(),
+        checked_at,
+        latency_ms,
+        chain_id: info.chain_id,
+        height: info.height,
+        block_time: info.block_time,
+        catching_up: info.catching_up,
+        error,
+    }
+}
+
+/// Checks all the endpoints concurrently, failures are reported in the
+/// `error` of each status
+pub async fn check_endpoints(
+    endpoints: &[Endpoint],
+    timeout: Duration,
+) -> Result<Vec<EndpointStatus>> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .stack()?;
+    Ok(join_all(
+        endpoints
+            .iter()
+            .map(|endpoint| check_endpoint(&client, endpoint, timeout)),
+    )
+    .await)
+}
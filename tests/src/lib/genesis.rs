@@ -0,0 +1,200 @@
+//! helpers for working with the JSON of genesis files
+
+use std::collections::BTreeMap;
+
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Result, StackableErr},
+    stacked_get, stacked_get_mut,
+};
+use serde_json::{json, Value};
+use u64_array_bigints::U256;
+
+/// Returns the address of any kind of account in `app_state.auth.accounts`,
+/// including module and vesting accounts
+pub fn account_address(account: &Value) -> Option<&str> {
+    account
+        .get("address")
+        .or_else(|| account.pointer("/base_account/address"))
+        .or_else(|| account.pointer("/base_vesting_account/base_account/address"))
+        .and_then(|address| address.as_str())
+}
+
+/// Returns the `@type` of an account, e.x.
+/// "/cosmos.vesting.v1beta1.PeriodicVestingAccount"
+pub fn account_type(account: &Value) -> Option<&str> {
+    account.get("@type").and_then(|ty| ty.as_str())
+}
+
+pub fn is_vesting_account(account: &Value) -> bool {
+    account.get("base_vesting_account").is_some()
+}
+
+pub fn is_module_account(account: &Value) -> bool {
+    account_type(account) == Some("/cosmos.auth.v1beta1.ModuleAccount")
+}
+
+/// Parses a list of `{"denom": ..., "amount": ...}` coins
+pub fn parse_coins(coins: &Value) -> Result<BTreeMap<String, u128>> {
+    let mut res = BTreeMap::<String, u128>::new();
+    for coin in coins.as_array().stack()? {
+        let denom = stacked_get!(coin["denom"]).as_str().stack()?;
+        let amount: u128 = stacked_get!(coin["amount"])
+            .as_str()
+            .stack()?
+            .parse()
+            .stack()?;
+        let total = res.entry(denom.to_owned()).or_default();
+        *total = total
+            .checked_add(amount)
+            .stack_err(|| "coin amount overflow")?;
+    }
+    Ok(res)
+}
+
+/// The inverse of `parse_coins`, sorted by denom as the SDK requires
+pub fn coins_to_value(coins: &BTreeMap<String, u128>) -> Value {
+    Value::Array(
+        coins
+            .iter()
+            .map(|(denom, amount)| json!({"denom": denom, "amount": amount.to_string()}))
+            .collect(),
+    )
+}
+
+/// Adds `coins` to the balance of `address` in `app_state.bank.balances`,
+/// creating the balance if it does not exist. Returns if the balance already
+/// existed.
+pub fn add_to_balance(
+    genesis: &mut Value,
+    address: &str,
+    coins: &BTreeMap<String, u128>,
+) -> Result<bool> {
+    let balances = stacked_get_mut!(genesis["app_state"]["bank"]["balances"])
+        .as_array_mut()
+        .stack()?;
+    for balance in balances.iter_mut() {
+        if stacked_get!(balance["address"]).as_str() == Some(address) {
+            let mut total = parse_coins(stacked_get!(balance["coins"])).stack()?;
+            for (denom, amount) in coins {
+                let entry = total.entry(denom.clone()).or_default();
+                *entry = entry
+                    .checked_add(*amount)
+                    .stack_err(|| "coin amount overflow")?;
+            }
+            *stacked_get_mut!(balance["coins"]) = coins_to_value(&total);
+            return Ok(true)
+        }
+    }
+    balances.push(json!({
+        "address": address,
+        "coins": coins_to_value(coins)
+    }));
+    Ok(false)
+}
+
+/// Recomputes `app_state.bank.supply` as the sum of all balances per denom
+pub fn recompute_supply(genesis: &mut Value) -> Result<BTreeMap<String, u128>> {
+    let mut supply = BTreeMap::<String, u128>::new();
+    for balance in stacked_get!(genesis["app_state"]["bank"]["balances"])
+        .as_array()
+        .stack()?
+    {
+        for (denom, amount) in parse_coins(stacked_get!(balance["coins"])).stack()? {
+            let total = supply.entry(denom).or_default();
+            *total = total.checked_add(amount).stack_err(|| "supply overflow")?;
+        }
+    }
+    *stacked_get_mut!(genesis["app_state"]["bank"]["supply"]) = coins_to_value(&supply);
+    Ok(supply)
+}
+
+/// Parses an integer or `sdk.Dec` string, truncating at the decimal point
+fn parse_truncated(s: &str) -> Result<U256> {
+    let int = s.split_once('.').map_or(s, |(int, _)| int);
+    U256::from_dec_or_hex_str(int).stack_err(|| format!("invalid amount {s}"))
+}
+
+/// A validator of `app_state.staking.validators`
+#[derive(Debug, Clone)]
+pub struct GenesisValidator {
+    pub operator_address: String,
+    pub moniker: String,
+    pub bonded: bool,
+    pub tokens: U256,
+    /// Truncated to an integer
+    pub delegator_shares: U256,
+}
+
+pub fn genesis_validators(genesis: &Value) -> Result<Vec<GenesisValidator>> {
+    let mut validators = vec![];
+    for validator in stacked_get!(genesis["app_state"]["staking"]["validators"])
+        .as_array()
+        .stack()?
+    {
+        validators.push(GenesisValidator {
+            operator_address: stacked_get!(validator["operator_address"])
+                .as_str()
+                .stack()?
+                .to_owned(),
+            moniker: validator
+                .pointer("/description/moniker")
+                .and_then(|moniker| moniker.as_str())
+                .unwrap_or_default()
+                .to_owned(),
+            bonded: validator.get("status").and_then(|status| status.as_str())
+                == Some("BOND_STATUS_BONDED"),
+            tokens: parse_truncated(stacked_get!(validator["tokens"]).as_str().stack()?).stack()?,
+            delegator_shares: parse_truncated(
+                stacked_get!(validator["delegator_shares"])
+                    .as_str()
+                    .stack()?,
+            )
+            .stack()?,
+        });
+    }
+    Ok(validators)
+}
+
+/// Sums the tokens that each delegator address has delegated in
+/// `app_state.staking.delegations`, regardless of the validator status
+pub fn delegated_amounts(genesis: &Value) -> Result<BTreeMap<String, u128>> {
+    let validators: BTreeMap<String, GenesisValidator> = genesis_validators(genesis)
+        .stack()?
+        .into_iter()
+        .map(|validator| (validator.operator_address.clone(), validator))
+        .collect();
+    let mut amounts = BTreeMap::<String, u128>::new();
+    for delegation in stacked_get!(genesis["app_state"]["staking"]["delegations"])
+        .as_array()
+        .stack()?
+    {
+        let address = stacked_get!(delegation["delegator_address"])
+            .as_str()
+            .stack()?;
+        let validator_address = stacked_get!(delegation["validator_address"])
+            .as_str()
+            .stack()?;
+        let validator = validators
+            .get(validator_address)
+            .stack_err(|| format!("delegation to unknown validator {validator_address}"))?;
+        let shares =
+            parse_truncated(stacked_get!(delegation["shares"]).as_str().stack()?).stack()?;
+
+        // delegated tokens = (shares * total_tokens) / total_shares
+        let tokens = shares
+            .checked_mul(validator.tokens)
+            .stack_err(|| "delegation overflow")?
+            .divide(validator.delegator_shares)
+            .stack_err(|| format!("validator {validator_address} has no shares"))?
+            .0
+            .try_resize_to_u128()
+            .stack_err(|| "delegation overflow")?;
+
+        // if multiple delegations from same address, add them up
+        let total = amounts.entry(address.to_owned()).or_default();
+        *total = total
+            .checked_add(tokens)
+            .stack_err(|| "delegation overflow")?;
+    }
+    Ok(amounts)
+}
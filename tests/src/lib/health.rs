@@ -0,0 +1,240 @@
+//! Chain and relayer health in the Prometheus text format, served by
+//! `health_exporter`
+
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use chrono::{TimeZone, Utc};
+use deep_space::{client::types::LatestBlock, Contact};
+use log::warn;
+use onomy_test_lib::super_orchestrator::stacked_errors::{Result, StackableErr};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{ibc::query_client_status, preflight::check_wallet, relayer_config::Network};
+
+/// Gauges grouped by metric name, rendered in the Prometheus text format
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// name to help text and samples
+    gauges: BTreeMap<&'static str, (&'static str, Vec<(String, f64)>)>,
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn gauge(
+        &mut self,
+        name: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| format!("{key}=\"{}\"", escape_label(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.gauges
+            .entry(name)
+            .or_insert_with(|| (help, vec![]))
+            .1
+            .push((labels, value));
+    }
+
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        for (name, (help, samples)) in &self.gauges {
+            writeln!(s, "# HELP {name} {help}").unwrap();
+            writeln!(s, "# TYPE {name} gauge").unwrap();
+            for (labels, value) in samples {
+                writeln!(s, "{name}{{{labels}}} {value}").unwrap();
+            }
+        }
+        s
+    }
+}
+
+async fn latest_block(grpc: &str, prefix: &str) -> Result<LatestBlock> {
+    let contact = Contact::new(grpc, Duration::from_secs(10), prefix).stack()?;
+    contact.get_latest_block().await.stack()
+}
+
+/// Adds the latest block metrics of every chain in the network, the IBC client
+/// metrics of every configured client, and the relayer balances if `mnemonic`
+/// is given. Failed queries are logged and reported through the `_up` metrics
+/// instead of failing the whole collection.
+pub async fn collect_metrics(network: &Network, mnemonic: Option<&str>) -> Metrics {
+    let mut metrics = Metrics::default();
+    let now = Utc::now();
+    for chain in &network.chains {
+        let chain_id = chain.chain_id.as_str();
+        let grpc = chain.grpc_addr();
+
+        let (block, syncing) = match latest_block(&grpc, &chain.prefix).await {
+            Ok(LatestBlock::Latest { block }) => (Some(block), false),
+            Ok(LatestBlock::Syncing { block }) => (Some(block), true),
+            Ok(LatestBlock::WaitingToStart) => (None, true),
+            Err(e) => {
+                warn!("could not get the latest block of {chain_id}: {e:?}");
+                metrics.gauge(
+                    "onomy_chain_up",
+                    "If the gRPC endpoint of the chain responded",
+                    &[("chain_id", chain_id)],
+                    0.0,
+                );
+                continue
+            }
+        };
+        metrics.gauge(
+            "onomy_chain_up",
+            "If the gRPC endpoint of the chain responded",
+            &[("chain_id", chain_id)],
+            1.0,
+        );
+        metrics.gauge(
+            "onomy_chain_syncing",
+            "If the node is still catching up, or is waiting for genesis",
+            &[("chain_id", chain_id)],
+            if syncing { 1.0 } else { 0.0 },
+        );
+        if let Some(header) = block.and_then(|block| block.header) {
+            metrics.gauge(
+                "onomy_chain_latest_height",
+                "Height of the latest block",
+                &[("chain_id", chain_id)],
+                header.height as f64,
+            );
+            if let Some(time) = header.time.and_then(|time| {
+                Utc.timestamp_opt(time.seconds, u32::try_from(time.nanos).ok()?)
+                    .single()
+            }) {
+                metrics.gauge(
+                    "onomy_chain_block_age_seconds",
+                    "Seconds since the time of the latest block",
+                    &[("chain_id", chain_id)],
+                    (now - time).num_milliseconds() as f64 / 1000.0,
+                );
+            }
+        }
+
+        for client_id in &chain.clients {
+            let status = match query_client_status(chain_id, &grpc, client_id).await {
+                Ok(status) => status,
+                Err(e) => {
+                    warn!("could not query client {client_id} on {chain_id}: {e:?}");
+                    metrics.gauge(
+                        "onomy_ibc_client_up",
+                        "If the IBC client could be queried",
+                        &[("chain_id", chain_id), ("client_id", client_id)],
+                        0.0,
+                    );
+                    continue
+                }
+            };
+            metrics.gauge(
+                "onomy_ibc_client_up",
+                "If the IBC client could be queried",
+                &[("chain_id", chain_id), ("client_id", client_id)],
+                1.0,
+            );
+            let labels = [
+                ("chain_id", chain_id),
+                ("client_id", client_id.as_str()),
+                (
+                    "counterparty_chain_id",
+                    status.counterparty_chain_id.as_str(),
+                ),
+            ];
+            match status.trusting_remaining(now) {
+                Ok(remaining) => metrics.gauge(
+                    "onomy_ibc_client_trusting_seconds_remaining",
+                    "Seconds until the IBC client expires, negative if it already has",
+                    &labels,
+                    remaining.num_seconds() as f64,
+                ),
+                Err(e) => warn!("trusting period of client {client_id} on {chain_id}: {e:?}"),
+            }
+            metrics.gauge(
+                "onomy_ibc_client_frozen",
+                "If the IBC client is frozen",
+                &labels,
+                if status.frozen { 1.0 } else { 0.0 },
+            );
+        }
+
+        if let Some(mnemonic) = mnemonic {
+            let status = match check_wallet(mnemonic, &chain.wallet_check()).await {
+                Ok(status) => status,
+                Err(e) => {
+                    warn!("could not query the relayer balance on {chain_id}: {e:?}");
+                    continue
+                }
+            };
+            metrics.gauge(
+                "onomy_relayer_balance",
+                "Balance of the relayer in the gas denom, in base units",
+                &[
+                    ("chain_id", chain_id),
+                    ("address", &status.address),
+                    ("denom", &chain.fee_denom),
+                ],
+                status.balance as f64,
+            );
+            if let Some(runway) = status.runway() {
+                metrics.gauge(
+                    "onomy_relayer_runway_updates",
+                    "Number of client updates at the max gas that the relayer balance covers",
+                    &[("chain_id", chain_id), ("address", &status.address)],
+                    runway as f64,
+                );
+            }
+        }
+    }
+    metrics
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    network: &Network,
+    mnemonic: Option<&str>,
+) -> Result<()> {
+    // only the request line is needed
+    let mut buf = vec![0u8; 4096];
+    let len = stream.read(&mut buf).await.stack()?;
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", collect_metrics(network, mnemonic).await.render())
+    } else {
+        ("404 Not Found", "see /metrics\n".to_owned())
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+         {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await.stack()?;
+    stream.shutdown().await.stack()
+}
+
+/// Serves `/metrics` on `listener` forever, collecting the metrics on every
+/// request
+pub async fn serve_metrics(
+    listener: TcpListener,
+    network: Network,
+    mnemonic: Option<String>,
+) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await.stack()?;
+        if let Err(e) = handle_connection(stream, &network, mnemonic.as_deref()).await {
+            warn!("failed to respond to {peer}: {e:?}");
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! A typed model of the Hermes config file, for when the config generated by
+//! `onomy_test_lib::hermes::write_hermes_config` is not customizable enough.
+//! See https://hermes.informal.systems/documentation/configuration/ for what
+//! the settings do.
+
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Result, StackableErr},
+    FileOptions,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::preflight::WalletCheck;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HermesConfig {
+    pub global: Global,
+    pub mode: Mode,
+    pub rest: Rest,
+    pub telemetry: Telemetry,
+    pub chains: Vec<Chain>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Global {
+    pub log_level: String,
+}
+
+impl Default for Global {
+    fn default() -> Self {
+        Self {
+            log_level: "info".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Mode {
+    pub clients: ModeClients,
+    pub connections: ModeEnabled,
+    pub channels: ModeEnabled,
+    pub packets: ModePackets,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeClients {
+    pub enabled: bool,
+    /// Refresh clients that underlie an open channel every 2/3 of their
+    /// trusting period
+    pub refresh: bool,
+    pub misbehaviour: bool,
+}
+
+impl Default for ModeClients {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            refresh: true,
+            misbehaviour: false,
+        }
+    }
+}
+
+/// For the connection and channel handshake workers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModeEnabled {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModePackets {
+    pub enabled: bool,
+    /// Interval in blocks at which pending packets are cleared, 0 disables
+    /// periodic clearing
+    pub clear_interval: u64,
+    pub clear_on_start: bool,
+    pub tx_confirmation: bool,
+    pub auto_register_counterparty_payee: bool,
+}
+
+impl Default for ModePackets {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            clear_interval: 0,
+            clear_on_start: true,
+            tx_confirmation: false,
+            auto_register_counterparty_payee: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rest {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for Rest {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_owned(),
+            port: 3000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Telemetry {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_owned(),
+            port: 3001,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventSource {
+    pub mode: String,
+    pub url: String,
+    pub batch_delay: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GasPrice {
+    pub price: f64,
+    pub denom: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustThreshold {
+    pub numerator: String,
+    pub denominator: String,
+}
+
+impl Default for TrustThreshold {
+    /// 1/3
+    fn default() -> Self {
+        Self {
+            numerator: "1".to_owned(),
+            denominator: "3".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketFilter {
+    /// "allow" or "deny"
+    pub policy: String,
+    /// (port, channel) pairs, which can use wildcards
+    pub list: Vec<(String, String)>,
+}
+
+impl PacketFilter {
+    pub fn allow(list: &[(&str, &str)]) -> Self {
+        Self {
+            policy: "allow".to_owned(),
+            list: list
+                .iter()
+                .map(|(port, channel)| (port.to_string(), channel.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chain {
+    pub id: String,
+    pub rpc_addr: String,
+    pub grpc_addr: String,
+    pub event_source: EventSource,
+    pub rpc_timeout: String,
+    pub account_prefix: String,
+    pub key_name: String,
+    pub store_prefix: String,
+    pub gas_price: GasPrice,
+    pub max_gas: u64,
+    pub clock_drift: String,
+    pub trusting_period: String,
+    pub trust_threshold: TrustThreshold,
+    #[serde(default)]
+    pub ccv_consumer_chain: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_filter: Option<PacketFilter>,
+}
+
+impl Chain {
+    /// Uses the default ports with `host`, these can be changed afterwards
+    pub fn new(
+        id: &str,
+        host: &str,
+        account_prefix: &str,
+        gas_price: f64,
+        gas_denom: &str,
+    ) -> Self {
+        Self {
+            id: id.to_owned(),
+            rpc_addr: format!("http://{host}:26657"),
+            grpc_addr: format!("http://{host}:9090"),
+            event_source: EventSource {
+                mode: "push".to_owned(),
+                url: format!("ws://{host}:26657/websocket"),
+                batch_delay: "200ms".to_owned(),
+            },
+            rpc_timeout: "15s".to_owned(),
+            account_prefix: account_prefix.to_owned(),
+            key_name: id.to_owned(),
+            store_prefix: "ibc".to_owned(),
+            gas_price: GasPrice {
+                price: gas_price,
+                denom: gas_denom.to_owned(),
+            },
+            max_gas: 10000000,
+            clock_drift: "5s".to_owned(),
+            trusting_period: "7days".to_owned(),
+            trust_threshold: TrustThreshold::default(),
+            ccv_consumer_chain: false,
+            packet_filter: None,
+        }
+    }
+}
+
+impl Chain {
+    pub fn wallet_check(&self) -> WalletCheck {
+        WalletCheck {
+            chain_id: self.id.clone(),
+            grpc: self.grpc_addr.clone(),
+            prefix: self.account_prefix.clone(),
+            gas_denom: self.gas_price.denom.clone(),
+            gas_price: self.gas_price.price,
+            max_gas: self.max_gas,
+        }
+    }
+}
+
+impl HermesConfig {
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).stack()
+    }
+
+    /// Writes the config to `{dir}/__tmp_hermes_config.toml`, which is where
+    /// `dockerfile_hermes("__tmp_hermes_config.toml")` expects it
+    pub async fn write(&self, dir: &str) -> Result<()> {
+        FileOptions::write_str(
+            &format!("{dir}/__tmp_hermes_config.toml"),
+            &self.to_toml().stack()?,
+        )
+        .await
+        .stack()
+    }
+}
@@ -0,0 +1,211 @@
+//! Parsing of Hermes' default text log format, e.x.
+//!
+//! ```text
+//! 2023-05-01T12:00:00.123456Z ERROR ThreadId(22) client{client=07-tendermint-0}: ...
+//! ```
+//!
+//! The span fields are used to find which chain, client, and channel a line is
+//! about. Hermes does not have a stable message format, so the success counts
+//! and known issues are found by matching substrings.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "TRACE" => Some(Self::Trace),
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARN" => Some(Self::Warn),
+            "ERROR" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Span fields that name the chain of a line, in order of preference
+const CHAIN_FIELDS: &[&str] = &["chain", "chain_id", "host_chain", "src_chain", "dst_chain"];
+const CLIENT_FIELDS: &[&str] = &["client", "client_id", "dst_client", "src_client"];
+const CHANNEL_FIELDS: &[&str] = &["channel", "channel_id", "src_channel", "dst_channel"];
+
+/// A problem that has a known cause, along with lowercase substrings that
+/// identify it
+#[derive(Debug, Clone, Copy)]
+pub struct KnownIssue {
+    pub name: &'static str,
+    pub patterns: &'static [&'static str],
+}
+
+pub const KNOWN_ISSUES: &[KnownIssue] = &[
+    KnownIssue {
+        name: "expired or frozen client",
+        patterns: &[
+            "client is expired",
+            "client state is expired",
+            "expired client",
+            "header is expired",
+            "client is frozen",
+            "client state is not active",
+        ],
+    },
+    KnownIssue {
+        name: "insufficient fees",
+        patterns: &["insufficient fee", "insufficient funds"],
+    },
+    KnownIssue {
+        name: "account sequence mismatch",
+        patterns: &["account sequence mismatch", "incorrect account sequence"],
+    },
+    KnownIssue {
+        name: "RPC timeout",
+        patterns: &[
+            "timed out",
+            "deadline has elapsed",
+            "request timeout",
+            "operation timed out",
+        ],
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: Level,
+    /// `key=value` fields from the spans and message, only the first
+    /// occurrence of each key is kept
+    pub fields: BTreeMap<String, String>,
+    /// Everything after the level and thread ID
+    pub message: String,
+}
+
+/// Removes ANSI color codes, which Hermes writes even when logging to a file
+pub fn strip_ansi(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip to the end of the control sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break
+                }
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+fn parse_fields(message: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    for token in message.split(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | ',')) {
+        if let Some((key, value)) = token.split_once('=') {
+            let value = value.trim_end_matches(':').trim_matches('"');
+            if key.is_empty()
+                || value.is_empty()
+                || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                continue
+            }
+            fields
+                .entry(key.to_owned())
+                .or_insert_with(|| value.to_owned());
+        }
+    }
+    fields
+}
+
+impl LogLine {
+    /// Returns `None` for lines that do not start with an optional timestamp
+    /// and a level, such as the continuation lines of multiline messages
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = strip_ansi(line);
+        let mut rest = line.trim_start();
+        let (first, after_first) = rest.split_once(char::is_whitespace)?;
+        let timestamp = DateTime::parse_from_rfc3339(first)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Utc));
+        if timestamp.is_some() {
+            rest = after_first.trim_start();
+        }
+        let (level, after_level) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let level = Level::parse(level)?;
+        rest = after_level.trim_start();
+        if rest.starts_with("ThreadId(") {
+            rest = rest.split_once(char::is_whitespace).map_or("", |x| x.1);
+        }
+        let message = rest.trim().to_owned();
+        Some(Self {
+            timestamp,
+            level,
+            fields: parse_fields(&message),
+            message,
+        })
+    }
+
+    fn first_field(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .find_map(|key| self.fields.get(*key))
+            .map(|s| s.as_str())
+    }
+
+    pub fn chain(&self) -> Option<&str> {
+        self.first_field(CHAIN_FIELDS)
+    }
+
+    pub fn client(&self) -> Option<&str> {
+        self.first_field(CLIENT_FIELDS)
+    }
+
+    pub fn channel(&self) -> Option<&str> {
+        self.first_field(CHANNEL_FIELDS)
+    }
+
+    /// The first known issue that this line matches, only warnings and errors
+    /// are checked
+    pub fn known_issue(&self) -> Option<&'static KnownIssue> {
+        if self.level < Level::Warn {
+            return None
+        }
+        let message = self.message.to_lowercase();
+        KNOWN_ISSUES.iter().find(|issue| {
+            issue
+                .patterns
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        })
+    }
+
+    /// If this is an info line about a successful client update
+    pub fn is_client_update(&self) -> bool {
+        if self.level != Level::Info {
+            return false
+        }
+        let message = self.message.to_lowercase();
+        !message.contains("fail")
+            && ["updateclient", "client updated", "updated client"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+    }
+
+    /// The number of times `event` (e.x. "WriteAcknowledgement") appears in an
+    /// info line, Hermes lists the events of the transactions it submits
+    pub fn count_event(&self, event: &str) -> usize {
+        if self.level != Level::Info {
+            return 0
+        }
+        self.message.matches(event).count()
+    }
+}
@@ -0,0 +1,457 @@
+//! IBC queries over gRPC
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use cosmos_sdk_proto::{
+    cosmos::{
+        base::query::v1beta1::PageRequest,
+        tx::v1beta1::{service_client::ServiceClient, GetTxsEventRequest, OrderBy},
+    },
+    ibc::{
+        core::{
+            channel::v1::{
+                query_client::QueryClient as ChannelQueryClient, IdentifiedChannel,
+                QueryChannelRequest, QueryConnectionChannelsRequest, QueryPacketCommitmentsRequest,
+                QueryUnreceivedPacketsRequest, State as ChannelState,
+            },
+            client::v1::{
+                query_client::QueryClient, QueryClientStateRequest, QueryConsensusStateRequest,
+            },
+            connection::v1::{
+                query_client::QueryClient as ConnectionQueryClient, ConnectionEnd,
+                QueryClientConnectionsRequest, QueryConnectionRequest, State as ConnectionState,
+            },
+        },
+        lightclients::tendermint::v1::{ClientState, ConsensusState},
+    },
+};
+use onomy_test_lib::super_orchestrator::stacked_errors::{ensure_eq, Error, Result, StackableErr};
+use prost::Message;
+
+pub const TENDERMINT_CLIENT_STATE_URL: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+pub const TENDERMINT_CONSENSUS_STATE_URL: &str = "/ibc.lightclients.tendermint.v1.ConsensusState";
+
+/// The state of a Tendermint light client hosted on some chain
+#[derive(Debug, Clone)]
+pub struct ClientStatus {
+    /// The chain hosting the client
+    pub host_chain_id: String,
+    pub client_id: String,
+    /// The chain that the client tracks
+    pub counterparty_chain_id: String,
+    pub revision_number: u64,
+    pub revision_height: u64,
+    pub trusting_period: Duration,
+    /// The timestamp of the latest consensus state, this is the time of the
+    /// last client update
+    pub last_update: DateTime<Utc>,
+    pub frozen: bool,
+}
+
+impl ClientStatus {
+    /// The time left until the client expires, negative if it already has
+    pub fn trusting_remaining(&self, now: DateTime<Utc>) -> Result<chrono::Duration> {
+        let trusting_period = chrono::Duration::from_std(self.trusting_period).stack()?;
+        Ok((self.last_update + trusting_period) - now)
+    }
+}
+
+fn to_std_duration(duration: &prost_types::Duration) -> Result<Duration> {
+    Ok(Duration::new(
+        u64::try_from(duration.seconds).stack()?,
+        u32::try_from(duration.nanos).stack()?,
+    ))
+}
+
+/// Queries the client state and latest consensus state of `client_id` on the
+/// chain `host_chain_id` served by `grpc`
+pub async fn query_client_status(
+    host_chain_id: &str,
+    grpc: &str,
+    client_id: &str,
+) -> Result<ClientStatus> {
+    let mut client = QueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+
+    let client_state = client
+        .client_state(QueryClientStateRequest {
+            client_id: client_id.to_owned(),
+        })
+        .await
+        .stack_err(|| format!("could not query client state of {client_id}"))?
+        .into_inner()
+        .client_state
+        .stack_err(|| format!("no client state for {client_id}"))?;
+    ensure_eq!(client_state.type_url, TENDERMINT_CLIENT_STATE_URL);
+    let client_state = ClientState::decode(client_state.value.as_slice()).stack()?;
+    let latest_height = client_state.latest_height.clone().stack()?;
+
+    let consensus_state = client
+        .consensus_state(QueryConsensusStateRequest {
+            client_id: client_id.to_owned(),
+            revision_number: latest_height.revision_number,
+            revision_height: latest_height.revision_height,
+            latest_height: true,
+        })
+        .await
+        .stack_err(|| format!("could not query consensus state of {client_id}"))?
+        .into_inner()
+        .consensus_state
+        .stack_err(|| format!("no consensus state for {client_id}"))?;
+    ensure_eq!(consensus_state.type_url, TENDERMINT_CONSENSUS_STATE_URL);
+    let consensus_state = ConsensusState::decode(consensus_state.value.as_slice()).stack()?;
+    let timestamp = consensus_state.timestamp.stack()?;
+    let last_update = Utc
+        .timestamp_opt(timestamp.seconds, u32::try_from(timestamp.nanos).stack()?)
+        .single()
+        .stack()?;
+
+    let frozen = client_state.frozen_height.map_or(false, |height| {
+        (height.revision_number | height.revision_height) != 0
+    });
+
+    Ok(ClientStatus {
+        host_chain_id: host_chain_id.to_owned(),
+        client_id: client_id.to_owned(),
+        counterparty_chain_id: client_state.chain_id,
+        revision_number: latest_height.revision_number,
+        revision_height: latest_height.revision_height,
+        trusting_period: to_std_duration(&client_state.trusting_period.stack()?).stack()?,
+        last_update,
+        frozen,
+    })
+}
+
+/// A connection end hosted on some chain
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub connection_id: String,
+    pub client_id: String,
+    pub state: ConnectionState,
+    pub counterparty_client_id: String,
+    /// Empty until the handshake reaches the counterparty
+    pub counterparty_connection_id: String,
+}
+
+/// Queries every connection end that uses `client_id` on the chain served by
+/// `grpc`
+pub async fn query_client_connections(grpc: &str, client_id: &str) -> Result<Vec<ConnectionInfo>> {
+    let mut client = ConnectionQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+
+    let connection_ids = match client
+        .client_connections(QueryClientConnectionsRequest {
+            client_id: client_id.to_owned(),
+        })
+        .await
+    {
+        Ok(response) => response.into_inner().connection_paths,
+        // the SDK returns this instead of an empty list
+        Err(status) if status.code() == tonic::Code::NotFound => return Ok(vec![]),
+        Err(status) => {
+            return Err(Error::from(format!(
+                "could not query connections of {client_id}: {status:?}"
+            )))
+        }
+    };
+
+    let mut res = vec![];
+    for connection_id in connection_ids {
+        let connection = client
+            .connection(QueryConnectionRequest {
+                connection_id: connection_id.clone(),
+            })
+            .await
+            .stack_err(|| format!("could not query connection {connection_id}"))?
+            .into_inner()
+            .connection
+            .stack_err(|| format!("no connection end for {connection_id}"))?;
+        res.push(ConnectionInfo::from_connection_end(connection_id, connection).stack()?);
+    }
+    Ok(res)
+}
+
+impl ConnectionInfo {
+    pub fn from_connection_end(connection_id: String, connection: ConnectionEnd) -> Result<Self> {
+        let counterparty = connection.counterparty.stack()?;
+        Ok(Self {
+            connection_id,
+            client_id: connection.client_id,
+            state: ConnectionState::from_i32(connection.state).stack()?,
+            counterparty_client_id: counterparty.client_id,
+            counterparty_connection_id: counterparty.connection_id,
+        })
+    }
+}
+
+pub async fn query_connection(grpc: &str, connection_id: &str) -> Result<ConnectionInfo> {
+    let mut client = ConnectionQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let connection = client
+        .connection(QueryConnectionRequest {
+            connection_id: connection_id.to_owned(),
+        })
+        .await
+        .stack_err(|| format!("could not query connection {connection_id}"))?
+        .into_inner()
+        .connection
+        .stack_err(|| format!("no connection end for {connection_id}"))?;
+    ConnectionInfo::from_connection_end(connection_id.to_owned(), connection)
+}
+
+/// A channel end hosted on some chain
+#[derive(Debug, Clone)]
+pub struct ChannelInfo {
+    pub port_id: String,
+    pub channel_id: String,
+    pub state: ChannelState,
+    pub connection_id: String,
+    pub counterparty_port_id: String,
+    /// Empty until the handshake reaches the counterparty
+    pub counterparty_channel_id: String,
+}
+
+impl ChannelInfo {
+    pub fn from_identified_channel(channel: IdentifiedChannel) -> Result<Self> {
+        let counterparty = channel.counterparty.stack()?;
+        Ok(Self {
+            state: ChannelState::from_i32(channel.state).stack()?,
+            connection_id: channel
+                .connection_hops
+                .into_iter()
+                .next()
+                .stack_err(|| format!("channel {} has no connection hops", channel.channel_id))?,
+            port_id: channel.port_id,
+            channel_id: channel.channel_id,
+            counterparty_port_id: counterparty.port_id,
+            counterparty_channel_id: counterparty.channel_id,
+        })
+    }
+}
+
+/// Queries every channel end over `connection_id` on the chain served by `grpc`
+pub async fn query_connection_channels(
+    grpc: &str,
+    connection_id: &str,
+) -> Result<Vec<ChannelInfo>> {
+    let mut client = ChannelQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let channels = client
+        .connection_channels(QueryConnectionChannelsRequest {
+            connection: connection_id.to_owned(),
+            pagination: None,
+        })
+        .await
+        .stack_err(|| format!("could not query channels of {connection_id}"))?
+        .into_inner()
+        .channels;
+    channels
+        .into_iter()
+        .map(ChannelInfo::from_identified_channel)
+        .collect()
+}
+
+pub async fn query_channel(grpc: &str, port_id: &str, channel_id: &str) -> Result<ChannelInfo> {
+    let mut client = ChannelQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let channel = client
+        .channel(QueryChannelRequest {
+            port_id: port_id.to_owned(),
+            channel_id: channel_id.to_owned(),
+        })
+        .await
+        .stack_err(|| format!("could not query channel {port_id}/{channel_id}"))?
+        .into_inner()
+        .channel
+        .stack_err(|| format!("no channel end for {port_id}/{channel_id}"))?;
+    ChannelInfo::from_identified_channel(IdentifiedChannel {
+        state: channel.state,
+        ordering: channel.ordering,
+        counterparty: channel.counterparty,
+        connection_hops: channel.connection_hops,
+        version: channel.version,
+        port_id: port_id.to_owned(),
+        channel_id: channel_id.to_owned(),
+    })
+}
+
+/// Returns the sequences of the packets sent over the channel that still have
+/// commitments, these are the packets that have not been received by the
+/// counterparty or whose acknowledgement has not been relayed back
+pub async fn query_packet_commitments(
+    grpc: &str,
+    port_id: &str,
+    channel_id: &str,
+) -> Result<Vec<u64>> {
+    let mut client = ChannelQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut sequences = vec![];
+    let mut next_key = vec![];
+    loop {
+        let response = client
+            .packet_commitments(QueryPacketCommitmentsRequest {
+                port_id: port_id.to_owned(),
+                channel_id: channel_id.to_owned(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 1000,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await
+            .stack_err(|| format!("could not query packet commitments of {port_id}/{channel_id}"))?
+            .into_inner();
+        sequences.extend(response.commitments.iter().map(|state| state.sequence));
+        next_key = response
+            .pagination
+            .map(|page| page.next_key)
+            .unwrap_or_default();
+        if next_key.is_empty() {
+            break
+        }
+    }
+    sequences.sort_unstable();
+    Ok(sequences)
+}
+
+/// Returns which of `sequences` have not been received on the receiving end of
+/// the channel
+pub async fn query_unreceived_packets(
+    grpc: &str,
+    port_id: &str,
+    channel_id: &str,
+    sequences: &[u64],
+) -> Result<Vec<u64>> {
+    if sequences.is_empty() {
+        return Ok(vec![])
+    }
+    let mut client = ChannelQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut unreceived = client
+        .unreceived_packets(QueryUnreceivedPacketsRequest {
+            port_id: port_id.to_owned(),
+            channel_id: channel_id.to_owned(),
+            packet_commitment_sequences: sequences.to_vec(),
+        })
+        .await
+        .stack_err(|| format!("could not query unreceived packets of {port_id}/{channel_id}"))?
+        .into_inner()
+        .sequences;
+    unreceived.sort_unstable();
+    Ok(unreceived)
+}
+
+/// Finds when the packet was sent by searching for the transaction that
+/// emitted its `send_packet` event on `grpc`. Packets sent from `EndBlock`,
+/// such as ICS VSC packets, have no transaction and are searched for in the
+/// block events with the CometBFT `block_search` of `rpc` instead. Returns
+/// `None` if neither finds the packet, e.x. if the node has pruned it.
+pub async fn query_send_packet_time(
+    grpc: &str,
+    rpc: &str,
+    port_id: &str,
+    channel_id: &str,
+    sequence: u64,
+) -> Result<Option<DateTime<Utc>>> {
+    let mut client = ServiceClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let response = match client
+        .get_txs_event(GetTxsEventRequest {
+            events: vec![
+                format!("send_packet.packet_src_port='{port_id}'"),
+                format!("send_packet.packet_src_channel='{channel_id}'"),
+                format!("send_packet.packet_sequence='{sequence}'"),
+            ],
+            pagination: None,
+            order_by: OrderBy::Asc as i32,
+        })
+        .await
+    {
+        Ok(response) => response.into_inner(),
+        Err(status) if status.code() == tonic::Code::NotFound => return Ok(None),
+        Err(status) => {
+            return Err(Error::from(format!(
+                "could not search for packet {sequence} of {port_id}/{channel_id}: {status:?}"
+            )))
+        }
+    };
+    match response.tx_responses.first() {
+        Some(tx) => Ok(Some(
+            DateTime::parse_from_rfc3339(&tx.timestamp)
+                .stack()?
+                .with_timezone(&Utc),
+        )),
+        None => query_block_send_packet_time(rpc, port_id, channel_id, sequence)
+            .await
+            .stack(),
+    }
+}
+
+/// Finds the time of the block whose `BeginBlock` or `EndBlock` emitted the
+/// `send_packet` event of the packet
+async fn query_block_send_packet_time(
+    rpc: &str,
+    port_id: &str,
+    channel_id: &str,
+    sequence: u64,
+) -> Result<Option<DateTime<Utc>>> {
+    let query = format!(
+        "\"send_packet.packet_src_port='{port_id}' AND \
+         send_packet.packet_src_channel='{channel_id}' AND \
+         send_packet.packet_sequence='{sequence}'\""
+    );
+    let url = format!("{rpc}/block_search");
+    let response = reqwest::Client::new()
+        .get(&url)
+        .query(&[
+            ("query", query.as_str()),
+            ("per_page", "1"),
+            ("order_by", "\"asc\""),
+        ])
+        .send()
+        .await
+        .stack_err(|| format!("GET {url} failed"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::from(format!("GET {url} returned {status}")))
+    }
+    let response: serde_json::Value = response
+        .json()
+        .await
+        .stack_err(|| format!("GET {url} did not return JSON"))?;
+    match response.pointer("/result/blocks/0/block/header/time") {
+        Some(time) => Ok(Some(
+            DateTime::parse_from_rfc3339(time.as_str().stack()?)
+                .stack()?
+                .with_timezone(&Utc),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Formats as e.x. "13d 4h 2m", with a leading '-' if negative
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let sign = if duration < chrono::Duration::zero() {
+        "-"
+    } else {
+        ""
+    };
+    let minutes = duration.num_minutes().abs();
+    format!(
+        "{sign}{}d {}h {}m",
+        minutes / (24 * 60),
+        (minutes / 60) % 24,
+        minutes % 60
+    )
+}
@@ -0,0 +1,158 @@
+//! Interchain Security queries over gRPC. `cosmos-sdk-proto` does not include
+//! the ICS protobufs, so the needed messages are written out here.
+
+use std::collections::BTreeMap;
+
+use onomy_test_lib::super_orchestrator::stacked_errors::{ensure_eq, Result, StackableErr};
+use tonic::{codec::ProstCodec, codegen::http::uri::PathAndQuery, transport::Endpoint};
+
+use crate::ibc::query_client_status;
+
+/// The client that a consumer chain has with its provider, this is always the
+/// first client created on the consumer
+pub const CONSUMER_CLIENT: &str = "07-tendermint-0";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryConsumerChainsRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryConsumerChainsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub chains: Vec<ConsumerChain>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ConsumerChain {
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    #[prost(string, tag = "2")]
+    pub client_id: String,
+}
+
+/// Makes a unary gRPC call at `path`, e.x.
+/// "/interchain_security.ccv.provider.v1.Query/QueryConsumerChains"
+pub async fn grpc_unary<Req, Res>(grpc: &str, path: &'static str, request: Req) -> Result<Res>
+where
+    Req: prost::Message + Send + Sync + 'static,
+    Res: prost::Message + Default + Send + Sync + 'static,
+{
+    let channel = Endpoint::from_shared(grpc.to_owned())
+        .stack()?
+        .connect()
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await.stack()?;
+    let response = client
+        .unary(
+            tonic::Request::new(request),
+            PathAndQuery::from_static(path),
+            ProstCodec::default(),
+        )
+        .await
+        .stack_err(|| format!("gRPC call to {path} failed"))?;
+    Ok(response.into_inner())
+}
+
+/// Returns the provider side client ID for every consumer chain ID, this is
+/// the same as `query provider list-consumer-chains`
+pub async fn query_consumer_chains(provider_grpc: &str) -> Result<BTreeMap<String, String>> {
+    let response: QueryConsumerChainsResponse = grpc_unary(
+        provider_grpc,
+        "/interchain_security.ccv.provider.v1.Query/QueryConsumerChains",
+        QueryConsumerChainsRequest {},
+    )
+    .await
+    .stack()?;
+    Ok(response
+        .chains
+        .into_iter()
+        .map(|chain| (chain.chain_id, chain.client_id))
+        .collect())
+}
+
+/// The pair of clients of a provider-consumer edge
+#[derive(Debug, Clone)]
+pub struct IcsClients {
+    pub provider_chain_id: String,
+    pub consumer_chain_id: String,
+    /// The client hosted on the provider that tracks the consumer
+    pub provider_client: String,
+    /// The client hosted on the consumer that tracks the provider
+    pub consumer_client: String,
+}
+
+/// Finds the provider side client of the consumer, and checks that the
+/// consumer side client is `CONSUMER_CLIENT` and that both clients track the
+/// expected counterparty
+pub async fn discover_ics_clients(
+    provider_chain_id: &str,
+    provider_grpc: &str,
+    consumer_chain_id: &str,
+    consumer_grpc: &str,
+) -> Result<IcsClients> {
+    let consumer_chains = query_consumer_chains(provider_grpc).await.stack()?;
+    let provider_client = consumer_chains.get(consumer_chain_id).stack_err(|| {
+        format!(
+            "consumer chain {consumer_chain_id} is not registered on {provider_chain_id}, the \
+             registered consumers are {:?}",
+            consumer_chains.keys().collect::<Vec<_>>()
+        )
+    })?;
+
+    let provider_side = query_client_status(provider_chain_id, provider_grpc, provider_client)
+        .await
+        .stack()?;
+    ensure_eq!(provider_side.counterparty_chain_id, consumer_chain_id);
+    let consumer_side = query_client_status(consumer_chain_id, consumer_grpc, CONSUMER_CLIENT)
+        .await
+        .stack()?;
+    ensure_eq!(consumer_side.counterparty_chain_id, provider_chain_id);
+
+    Ok(IcsClients {
+        provider_chain_id: provider_chain_id.to_owned(),
+        consumer_chain_id: consumer_chain_id.to_owned(),
+        provider_client: provider_client.clone(),
+        consumer_client: CONSUMER_CLIENT.to_owned(),
+    })
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryValidatorConsumerAddrRequest {
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    #[prost(string, tag = "2")]
+    pub provider_address: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryValidatorConsumerAddrResponse {
+    #[prost(string, tag = "1")]
+    pub consumer_address: String,
+}
+
+/// Returns the consumer consensus address that the validator with the provider
+/// consensus address `provider_address` assigned on `consumer_chain_id`, or
+/// `None` if no key was assigned and the provider key is used
+pub async fn query_validator_consumer_addr(
+    provider_grpc: &str,
+    consumer_chain_id: &str,
+    provider_address: &str,
+) -> Result<Option<String>> {
+    let response: QueryValidatorConsumerAddrResponse = grpc_unary(
+        provider_grpc,
+        "/interchain_security.ccv.provider.v1.Query/QueryValidatorConsumerAddr",
+        QueryValidatorConsumerAddrRequest {
+            chain_id: consumer_chain_id.to_owned(),
+            provider_address: provider_address.to_owned(),
+        },
+    )
+    .await
+    .stack()?;
+    // the provider returns an empty address if no key was assigned
+    if response.consumer_address.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(response.consumer_address))
+    }
+}
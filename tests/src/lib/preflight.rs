@@ -0,0 +1,131 @@
+//! Checks that the relayer key can pay for client updates before a relayer is
+//! started, since updates and relays fail silently once the key runs out of
+//! fee tokens
+
+use std::time::Duration;
+
+use deep_space::Contact;
+use log::{info, warn};
+use onomy_test_lib::super_orchestrator::stacked_errors::{Error, Result, StackableErr};
+
+use crate::get_private_key;
+
+/// The preflight arguments of the relayer binaries
+#[derive(clap::Args, Debug, Clone)]
+pub struct RunwayArgs {
+    /// Fail before starting if the relayer balance on any chain covers fewer
+    /// client updates than this
+    #[arg(long, default_value_t = 100)]
+    pub min_runway: u64,
+    /// Only warn about balances below `--min-runway`
+    #[arg(long)]
+    pub allow_low_balance: bool,
+}
+
+/// What is needed to find the relayer balance on a chain and what a client
+/// update costs there
+#[derive(Debug, Clone)]
+pub struct WalletCheck {
+    pub chain_id: String,
+    pub grpc: String,
+    pub prefix: String,
+    pub gas_denom: String,
+    pub gas_price: f64,
+    pub max_gas: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletStatus {
+    pub chain_id: String,
+    pub address: String,
+    pub balance: u128,
+    /// The most that a client update can cost, at the max gas
+    pub update_cost: u128,
+}
+
+impl WalletStatus {
+    /// The number of client updates the balance covers, `None` if updates are
+    /// free
+    pub fn runway(&self) -> Option<u128> {
+        if self.update_cost == 0 {
+            None
+        } else {
+            Some(self.balance / self.update_cost)
+        }
+    }
+}
+
+/// Derives the relayer address from `mnemonic` the same way Hermes does, and
+/// queries its balance in the gas denom
+pub async fn check_wallet(mnemonic: &str, check: &WalletCheck) -> Result<WalletStatus> {
+    let prefix = &check.prefix;
+    let address = get_private_key(mnemonic.trim())
+        .stack()?
+        .to_address(prefix)
+        .stack()?;
+    let contact = Contact::new(&check.grpc, Duration::from_secs(30), prefix).stack()?;
+    let mut balance = 0;
+    for coin in contact
+        .get_balances(address)
+        .await
+        .stack_err(|| format!("could not query balances on {}", check.chain_id))?
+    {
+        if coin.denom == check.gas_denom {
+            balance = coin.amount.try_resize_to_u128().stack()?;
+        }
+    }
+    Ok(WalletStatus {
+        chain_id: check.chain_id.clone(),
+        address: address.to_bech32(prefix).stack()?,
+        balance,
+        update_cost: (check.gas_price * (check.max_gas as f64)).ceil() as u128,
+    })
+}
+
+/// Checks the relayer balance on every chain, returning an error if any
+/// balance covers fewer than `--min-runway` client updates unless
+/// `--allow-low-balance` is set
+pub async fn check_wallets(
+    mnemonic: &str,
+    checks: &[WalletCheck],
+    runway_args: &RunwayArgs,
+) -> Result<()> {
+    let min_runway = runway_args.min_runway;
+    let mut problems = vec![];
+    for check in checks {
+        let status = check_wallet(mnemonic, check).await.stack()?;
+        let denom = &check.gas_denom;
+        match status.runway() {
+            Some(runway) => {
+                info!(
+                    "relayer {} on {} has {}{denom}, enough for {runway} client updates",
+                    status.address, status.chain_id, status.balance
+                );
+                if runway < u128::from(min_runway) {
+                    problems.push(format!(
+                        "relayer {} on {} has {}{denom} which covers {runway} client updates, \
+                         fewer than the minimum of {min_runway}",
+                        status.address, status.chain_id, status.balance
+                    ));
+                }
+            }
+            None => info!(
+                "relayer {} on {} has {}{denom}, and the gas price is zero",
+                status.address, status.chain_id, status.balance
+            ),
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else if runway_args.allow_low_balance {
+        for problem in problems {
+            warn!("{problem}");
+        }
+        Ok(())
+    } else {
+        Err(Error::from(format!(
+            "relayer balances are low, fund them or pass `--allow-low-balance`:\n{}",
+            problems.join("\n")
+        )))
+    }
+}
@@ -0,0 +1,330 @@
+//! Registry of the networks and chains that the binaries connect to, so that
+//! chain IDs, nodes, and denoms are not hardcoded in every binary. See
+//! `tests/configs/networks.ron`.
+
+use std::collections::BTreeMap;
+
+use clap::Parser;
+use onomy_test_lib::{
+    dockerfiles::{dockerfile_onexd, dockerfile_onomyd},
+    super_orchestrator::{
+        stacked_errors::{Error, Result, StackableErr},
+        FileOptions,
+    },
+    Args,
+};
+use serde::{Deserialize, Serialize};
+
+/// The `version` that `networks.ron` must have
+pub const NETWORKS_VERSION: u32 = 1;
+pub const DEFAULT_RPC_PORT: u16 = 26657;
+pub const DEFAULT_GRPC_PORT: u16 = 9090;
+pub const DEFAULT_REST_PORT: u16 = 1317;
+
+/// `onomy_test_lib::Args` with a network profile. `--node` and `--grpc`
+/// override the addresses of the resolved chain.
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct ProfileArgs {
+    #[command(flatten)]
+    pub args: Args,
+    /// The network in `--networks-path` to use, this is required for
+    /// resolving chains so that a binary never defaults to the wrong network
+    #[arg(long)]
+    pub network: Option<String>,
+    #[arg(long, default_value_t = String::from("./tests/configs/networks.ron"))]
+    pub networks_path: String,
+    /// Overrides the chain ID of the resolved chain
+    #[arg(long)]
+    pub chain_id: Option<String>,
+    /// Overrides the fee denom of the resolved chain
+    #[arg(long)]
+    pub fee_denom: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Networks {
+    pub version: u32,
+    /// Networks by name, e.x. "testnet" and "mainnet"
+    pub networks: BTreeMap<String, NetworkProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    /// Chains by name, e.x. "onomy" and "onex"
+    pub chains: BTreeMap<String, ChainProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainProfile {
+    pub chain_id: String,
+    /// The daemon binary, e.x. "onomyd"
+    pub daemon: String,
+    /// The hostname or IP of the node, without a port
+    pub host: String,
+    #[serde(default)]
+    pub rpc_port: Option<u16>,
+    #[serde(default)]
+    pub grpc_port: Option<u16>,
+    #[serde(default)]
+    pub rest_port: Option<u16>,
+    /// The event websocket follows the RPC port unless this is set, only the
+    /// relayer binaries use it
+    #[serde(default)]
+    pub event_port: Option<u16>,
+    /// Full URLs for nodes that are not reachable at `host` with a port, e.x.
+    /// behind an HTTPS proxy. These are used instead of `host` and the ports.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    #[serde(default)]
+    pub grpc_url: Option<String>,
+    #[serde(default)]
+    pub event_url: Option<String>,
+    pub prefix: String,
+    pub denom: String,
+    /// The denom that fees and the relayer's gas are paid in
+    pub fee_denom: String,
+    /// If this is the ICS provider of the consumers in the network
+    #[serde(default)]
+    pub ccv_provider: bool,
+    /// If this is an ICS consumer chain
+    #[serde(default)]
+    pub ccv_consumer: bool,
+    /// The gas price and max gas that Hermes is configured with, which are
+    /// also used to estimate how many client updates the relayer balance
+    /// covers. `relayer_config::DEFAULT_GAS_PRICE` and
+    /// `relayer_config::DEFAULT_MAX_GAS` are used if these are not set.
+    #[serde(default)]
+    pub gas_price: Option<f64>,
+    #[serde(default)]
+    pub max_gas: Option<u64>,
+    /// IDs of the clients hosted on this chain that `insure_relayers` should
+    /// refresh. The provider has a client for every consumer, and the
+    /// consumers have one client with their provider. If left empty on an ICS
+    /// chain, the clients are discovered with `Network::resolve_ics_clients`.
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// (port, channel) pairs hosted on this chain that `packet_health` should
+    /// monitor besides the ICS channels, and that `hermes_manual` relays. The
+    /// counterparty chain must also be in the network.
+    #[serde(default)]
+    pub channels: Vec<(String, String)>,
+}
+
+/// A chain profile with the addresses filled in and the CLI overrides applied,
+/// this is what the binaries use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedChain {
+    /// The name of the chain in its network
+    pub name: String,
+    pub chain_id: String,
+    pub daemon: String,
+    /// The CometBFT RPC address, for `--node`
+    pub node: String,
+    pub grpc: String,
+    pub rest: String,
+    pub prefix: String,
+    pub denom: String,
+    pub fee_denom: String,
+}
+
+impl Networks {
+    pub async fn load(path: &str) -> Result<Self> {
+        let s = FileOptions::read_to_string(path).await.stack()?;
+        let networks: Self =
+            ron::from_str(&s).stack_err(|| format!("failed to parse networks {path}"))?;
+        if networks.version != NETWORKS_VERSION {
+            return Err(Error::from(format!(
+                "{path} has version {}, but version {NETWORKS_VERSION} is expected",
+                networks.version
+            )))
+        }
+        Ok(networks)
+    }
+
+    pub fn network(&self, name: &str) -> Result<&NetworkProfile> {
+        self.networks.get(name).stack_err(|| {
+            format!(
+                "network \"{name}\" is not in the networks profile, the options are {:?}",
+                self.networks.keys().collect::<Vec<_>>()
+            )
+        })
+    }
+}
+
+impl NetworkProfile {
+    pub fn chain(&self, name: &str) -> Result<&ChainProfile> {
+        self.chains.get(name).stack_err(|| {
+            format!(
+                "chain \"{name}\" is not in the network, the options are {:?}",
+                self.chains.keys().collect::<Vec<_>>()
+            )
+        })
+    }
+}
+
+impl ChainProfile {
+    pub fn rpc_addr(&self) -> String {
+        self.rpc_url.clone().unwrap_or_else(|| {
+            format!(
+                "http://{}:{}",
+                self.host,
+                self.rpc_port.unwrap_or(DEFAULT_RPC_PORT)
+            )
+        })
+    }
+
+    pub fn grpc_addr(&self) -> String {
+        self.grpc_url.clone().unwrap_or_else(|| {
+            format!(
+                "http://{}:{}",
+                self.host,
+                self.grpc_port.unwrap_or(DEFAULT_GRPC_PORT)
+            )
+        })
+    }
+
+    pub fn rest_addr(&self) -> String {
+        format!(
+            "http://{}:{}",
+            self.host,
+            self.rest_port.unwrap_or(DEFAULT_REST_PORT)
+        )
+    }
+
+    /// The CometBFT event websocket
+    pub fn event_addr(&self) -> String {
+        self.event_url.clone().unwrap_or_else(|| {
+            format!(
+                "ws://{}:{}/websocket",
+                self.host,
+                self.event_port
+                    .or(self.rpc_port)
+                    .unwrap_or(DEFAULT_RPC_PORT)
+            )
+        })
+    }
+
+    pub fn resolve(&self, name: &str) -> ResolvedChain {
+        ResolvedChain {
+            name: name.to_owned(),
+            chain_id: self.chain_id.clone(),
+            daemon: self.daemon.clone(),
+            node: self.rpc_addr(),
+            grpc: self.grpc_addr(),
+            rest: self.rest_addr(),
+            prefix: self.prefix.clone(),
+            denom: self.denom.clone(),
+            fee_denom: self.fee_denom.clone(),
+        }
+    }
+}
+
+impl ProfileArgs {
+    /// Loads the network profile
+    pub async fn network_profile(&self) -> Result<NetworkProfile> {
+        let network = self.network.as_deref().stack_err(|| "need --network")?;
+        Ok(Networks::load(&self.networks_path)
+            .await
+            .stack()?
+            .network(network)
+            .stack()?
+            .clone())
+    }
+
+    /// Resolves the chain `name` of `--network`, with `--node`, `--grpc`,
+    /// `--chain-id`, and `--fee-denom` applied
+    pub async fn resolve(&self, name: &str) -> Result<ResolvedChain> {
+        let mut chain = self
+            .network_profile()
+            .await
+            .stack()?
+            .chain(name)
+            .stack()?
+            .resolve(name);
+        self.apply_overrides(&mut chain);
+        Ok(chain)
+    }
+
+    /// Resolves the chain `name` of an already loaded `network`, for binaries
+    /// that go over every chain of a network. The overrides are only applied
+    /// if `name` is the `only` chain that the binary was restricted to.
+    pub fn resolve_in(
+        &self,
+        network: &NetworkProfile,
+        name: &str,
+        only: Option<&str>,
+    ) -> Result<ResolvedChain> {
+        let mut chain = network.chain(name).stack()?.resolve(name);
+        if only == Some(name) {
+            self.apply_overrides(&mut chain);
+        } else if only.is_none() && self.has_overrides() {
+            return Err(Error::from(
+                "`--node`, `--grpc`, `--chain-id`, and `--fee-denom` need `--chain` to select the \
+                 chain that they apply to",
+            ))
+        }
+        Ok(chain)
+    }
+
+    /// If any of the single chain overrides were given
+    pub fn has_overrides(&self) -> bool {
+        self.args.node.is_some()
+            || self.args.grpc.is_some()
+            || self.chain_id.is_some()
+            || self.fee_denom.is_some()
+    }
+
+    fn apply_overrides(&self, chain: &mut ResolvedChain) {
+        if let Some(ref node) = self.args.node {
+            chain.node = node.clone();
+        }
+        if let Some(ref grpc) = self.args.grpc {
+            chain.grpc = grpc.clone();
+        }
+        if let Some(ref chain_id) = self.chain_id {
+            chain.chain_id = chain_id.clone();
+        }
+        if let Some(ref fee_denom) = self.fee_denom {
+            chain.fee_denom = fee_denom.clone();
+        }
+    }
+}
+
+impl ResolvedChain {
+    /// The dockerfile with the daemon of this chain installed, new daemons need
+    /// to be added here
+    pub fn dockerfile(&self) -> Result<String> {
+        match self.daemon.as_str() {
+            "onomyd" => Ok(dockerfile_onomyd()),
+            "onexd" => Ok(dockerfile_onexd()),
+            daemon => Err(Error::from(format!(
+                "there is no dockerfile for the daemon \"{daemon}\" of chain {}",
+                self.chain_id
+            ))),
+        }
+    }
+
+    /// Where the container runner of a binary places the resolved chain
+    pub fn resolved_path(name: &str) -> String {
+        format!("./tests/resources/tmp/{name}_profile.ron")
+    }
+
+    /// Loads the chain written by the container runner with `write_resolved`,
+    /// from inside the container
+    pub async fn load_resolved(name: &str) -> Result<Self> {
+        let s = FileOptions::read_to_string(&format!("/resources/tmp/{name}_profile.ron"))
+            .await
+            .stack()?;
+        ron::from_str(&s).stack()
+    }
+
+    pub async fn write_resolved(&self) -> Result<()> {
+        FileOptions::write_str(
+            &Self::resolved_path(&self.name),
+            &ron::to_string(self).stack()?,
+        )
+        .await
+        .stack()
+    }
+}
@@ -0,0 +1,161 @@
+//! Named cosmovisor and gRPC queries that `query_chain --recipe` runs
+//! non-interactively, see `tests/configs/recipes/`
+
+use std::{path::Path, time::Duration};
+
+use deep_space::{client::types::LatestBlock, Address, Coin, Contact};
+use log::{info, warn};
+use onomy_test_lib::{
+    cosmovisor::sh_cosmovisor_no_debug,
+    super_orchestrator::{
+        stacked_errors::{Error, Result, StackableErr},
+        FileOptions,
+    },
+    yaml_str_to_json_value,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{ibc::query_client_status, profiles::ResolvedChain};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    pub queries: Vec<RecipeQuery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeQuery {
+    /// The output is saved as `<name>.json`
+    pub name: String,
+    pub query: Query,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Query {
+    /// Arguments to `cosmovisor run`, e.x. `["query slashing signing-infos"]`.
+    /// The YAML output is converted to JSON.
+    Cosmovisor(Vec<String>),
+    Grpc(GrpcQuery),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GrpcQuery {
+    LatestBlock,
+    TotalSupply,
+    Balances { address: String },
+    ClientStatus { client_id: String },
+}
+
+fn coins_to_json(coins: &[Coin]) -> Value {
+    coins
+        .iter()
+        .map(|coin| json!({"denom": coin.denom, "amount": coin.amount.to_string()}))
+        .collect()
+}
+
+impl GrpcQuery {
+    pub async fn run(&self, chain: &ResolvedChain) -> Result<Value> {
+        let contact = Contact::new(&chain.grpc, Duration::from_secs(30), &chain.prefix).stack()?;
+        match self {
+            GrpcQuery::LatestBlock => {
+                let (block, syncing) = match contact.get_latest_block().await.stack()? {
+                    LatestBlock::Latest { block } => (Some(block), false),
+                    LatestBlock::Syncing { block } => (Some(block), true),
+                    LatestBlock::WaitingToStart => (None, true),
+                };
+                let header = block.and_then(|block| block.header);
+                Ok(json!({
+                    "syncing": syncing,
+                    "chain_id": header.as_ref().map(|header| header.chain_id.clone()),
+                    "height": header.as_ref().map(|header| header.height),
+                    "time": header
+                        .as_ref()
+                        .and_then(|header| header.time.as_ref())
+                        .map(|time| json!({"seconds": time.seconds, "nanos": time.nanos})),
+                }))
+            }
+            GrpcQuery::TotalSupply => {
+                Ok(coins_to_json(&contact.query_total_supply().await.stack()?))
+            }
+            GrpcQuery::Balances { address } => {
+                let address = Address::from_bech32(address.clone()).stack()?;
+                Ok(coins_to_json(&contact.get_balances(address).await.stack()?))
+            }
+            GrpcQuery::ClientStatus { client_id } => {
+                let status = query_client_status(&chain.chain_id, &chain.grpc, client_id)
+                    .await
+                    .stack()?;
+                Ok(json!({
+                    "host_chain_id": status.host_chain_id,
+                    "client_id": status.client_id,
+                    "counterparty_chain_id": status.counterparty_chain_id,
+                    "revision_number": status.revision_number,
+                    "revision_height": status.revision_height,
+                    "trusting_period_seconds": status.trusting_period.as_secs(),
+                    "last_update": status.last_update.to_rfc3339(),
+                    "frozen": status.frozen,
+                }))
+            }
+        }
+    }
+}
+
+impl Query {
+    pub async fn run(&self, chain: &ResolvedChain) -> Result<Value> {
+        match self {
+            Query::Cosmovisor(args) => {
+                let output = sh_cosmovisor_no_debug(args).await.stack()?;
+                yaml_str_to_json_value(&output).stack()
+            }
+            Query::Grpc(query) => query.run(chain).await.stack(),
+        }
+    }
+}
+
+impl Recipe {
+    pub async fn load(path: &str) -> Result<Self> {
+        let s = FileOptions::read_to_string(path).await.stack()?;
+        ron::from_str(&s).stack_err(|| format!("failed to parse recipe {path}"))
+    }
+
+    /// The name of the recipe at `path`, which is its file stem
+    pub fn name(path: &str) -> Result<String> {
+        Ok(Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .stack_err(|| format!("recipe path {path} has no file name"))?
+            .to_owned())
+    }
+
+    /// Runs every query and writes the results to `<output_dir>/<name>.json`.
+    /// Failed queries are logged and do not stop the others, but an error
+    /// listing them is returned at the end.
+    pub async fn run(&self, chain: &ResolvedChain, output_dir: &str) -> Result<()> {
+        tokio::fs::create_dir_all(output_dir)
+            .await
+            .stack_err(|| format!("could not create {output_dir}"))?;
+        let mut failures = vec![];
+        for query in &self.queries {
+            let value = match query.query.run(chain).await {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("query {} failed: {e:?}", query.name);
+                    failures.push(query.name.clone());
+                    continue
+                }
+            };
+            let path = format!("{output_dir}/{}.json", query.name);
+            FileOptions::write_str(&path, &serde_json::to_string_pretty(&value).stack()?)
+                .await
+                .stack()?;
+            info!("wrote {path}");
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::from(format!(
+                "queries {failures:?} of the recipe failed"
+            )))
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! The relayer view of a network in `tests/configs/networks.ron`, so that
+//! adding a consumer chain or client does not need a recompile
+
+use clap::Parser;
+use log::info;
+use onomy_test_lib::super_orchestrator::{
+    stacked_errors::{Error, Result, StackableErr},
+    FileOptions,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hermes_config::{Chain, HermesConfig},
+    ics::discover_ics_clients,
+    preflight::{check_wallets, RunwayArgs, WalletCheck},
+    profiles::{ChainProfile, NetworkProfile, Networks, ProfileArgs},
+};
+
+/// Where the container runner of a relayer binary places the resolved
+/// `Network` for the container side
+pub const RESOLVED_NETWORK_PATH: &str = "./tests/resources/tmp/relayer_network.ron";
+/// `RESOLVED_NETWORK_PATH` as seen from inside a container
+pub const CONTAINER_RESOLVED_NETWORK_PATH: &str = "/resources/tmp/relayer_network.ron";
+
+/// The gas price and max gas that Hermes is configured with for chains that do
+/// not set them
+pub const DEFAULT_GAS_PRICE: f64 = 1.0;
+pub const DEFAULT_MAX_GAS: u64 = 10000000;
+
+/// `ProfileArgs` with the additional arguments of the relayer binaries
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct RelayerArgs {
+    #[command(flatten)]
+    pub profile_args: ProfileArgs,
+    #[command(flatten)]
+    pub runway_args: RunwayArgs,
+}
+
+/// The chains of a network profile, the relayer binaries use every chain of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Network {
+    pub chains: Vec<ChainProfile>,
+}
+
+/// The relayer settings of a chain
+impl ChainProfile {
+    /// The Hermes config of this chain, the relayer balance checks use the
+    /// same gas price and max gas
+    pub fn hermes_chain(&self) -> Chain {
+        let mut chain = Chain::new(
+            &self.chain_id,
+            &self.host,
+            &self.prefix,
+            self.gas_price.unwrap_or(DEFAULT_GAS_PRICE),
+            &self.fee_denom,
+        );
+        chain.rpc_addr = self.rpc_addr();
+        chain.grpc_addr = self.grpc_addr();
+        chain.event_source.url = self.event_addr();
+        chain.max_gas = self.max_gas.unwrap_or(DEFAULT_MAX_GAS);
+        chain.ccv_consumer_chain = self.ccv_consumer;
+        chain
+    }
+
+    pub fn wallet_check(&self) -> WalletCheck {
+        self.hermes_chain().wallet_check()
+    }
+}
+
+impl RelayerArgs {
+    /// Loads `--network`
+    pub async fn network(&self) -> Result<Network> {
+        Network::from_args(&self.profile_args).await.stack()
+    }
+}
+
+impl Network {
+    pub fn from_profile(network: &NetworkProfile) -> Self {
+        Self {
+            chains: network.chains.values().cloned().collect(),
+        }
+    }
+
+    /// Loads the network `name` of the networks profile at `path`
+    pub async fn load(path: &str, name: &str) -> Result<Self> {
+        Ok(Self::from_profile(
+            Networks::load(path).await.stack()?.network(name).stack()?,
+        ))
+    }
+
+    /// Loads `--network`. Every chain of the network is used, so the single
+    /// chain overrides of `ProfileArgs` are not supported.
+    pub async fn from_args(profile_args: &ProfileArgs) -> Result<Self> {
+        if profile_args.has_overrides() {
+            return Err(Error::from(
+                "`--node`, `--grpc`, `--chain-id`, and `--fee-denom` are not supported by the \
+                 binaries that use every chain of the network, edit the networks profile instead",
+            ))
+        }
+        Ok(Self::from_profile(
+            &profile_args.network_profile().await.stack()?,
+        ))
+    }
+
+    /// Loads the network written by the container runner to
+    /// `RESOLVED_NETWORK_PATH`
+    pub async fn load_resolved() -> Result<Self> {
+        let s = FileOptions::read_to_string(CONTAINER_RESOLVED_NETWORK_PATH)
+            .await
+            .stack()?;
+        ron::from_str(&s).stack()
+    }
+
+    pub async fn write_resolved(&self) -> Result<()> {
+        FileOptions::write_str(RESOLVED_NETWORK_PATH, &ron::to_string(self).stack()?)
+            .await
+            .stack()
+    }
+
+    /// Returns the provider and consumer chains of the network
+    pub fn ics_chains(&self) -> Result<(&ChainProfile, Vec<&ChainProfile>)> {
+        let mut providers = self.chains.iter().filter(|chain| chain.ccv_provider);
+        let provider = providers
+            .next()
+            .stack_err(|| "no chain in the network has `ccv_provider: true`")?;
+        if providers.next().is_some() {
+            return Err(Error::from("more than one chain has `ccv_provider: true`"))
+        }
+        let consumers: Vec<&ChainProfile> = self
+            .chains
+            .iter()
+            .filter(|chain| chain.ccv_consumer)
+            .collect();
+        if consumers.is_empty() {
+            return Err(Error::from(format!(
+                "the network of provider {} has no chain with `ccv_consumer: true`, its consumers \
+                 need to be added to the networks profile",
+                provider.chain_id
+            )))
+        }
+        Ok((provider, consumers))
+    }
+
+    /// Fills in the `clients` of ICS chains that were left empty, by querying
+    /// the provider's consumer chain list
+    pub async fn resolve_ics_clients(&mut self) -> Result<()> {
+        if !self
+            .chains
+            .iter()
+            .any(|chain| (chain.ccv_provider || chain.ccv_consumer) && chain.clients.is_empty())
+        {
+            return Ok(())
+        }
+        let (provider, consumers) = self.ics_chains().stack()?;
+        let mut discovered = vec![];
+        for consumer in consumers {
+            discovered.push(
+                discover_ics_clients(
+                    &provider.chain_id,
+                    &provider.grpc_addr(),
+                    &consumer.chain_id,
+                    &consumer.grpc_addr(),
+                )
+                .await
+                .stack()?,
+            );
+        }
+        for chain in &mut self.chains {
+            if !chain.clients.is_empty() {
+                continue
+            }
+            for clients in &discovered {
+                if chain.ccv_provider {
+                    info!(
+                        "discovered client {} on {} for {}",
+                        clients.provider_client, chain.chain_id, clients.consumer_chain_id
+                    );
+                    chain.clients.push(clients.provider_client.clone());
+                } else if chain.chain_id == clients.consumer_chain_id {
+                    chain.clients.push(clients.consumer_client.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the relayer balance on every chain, see
+    /// `preflight::check_wallets`
+    pub async fn check_wallets(&self, mnemonic: &str, args: &RelayerArgs) -> Result<()> {
+        let checks: Vec<WalletCheck> = self
+            .chains
+            .iter()
+            .map(|chain| chain.wallet_check())
+            .collect();
+        check_wallets(mnemonic, &checks, &args.runway_args)
+            .await
+            .stack()
+    }
+
+    /// The Hermes config that the relayer binaries write, with the gas
+    /// settings that `check_wallets` checks against
+    pub fn hermes_config(&self) -> HermesConfig {
+        HermesConfig {
+            chains: self
+                .chains
+                .iter()
+                .map(|chain| chain.hermes_chain())
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
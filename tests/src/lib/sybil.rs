@@ -0,0 +1,199 @@
+//! Heuristics for flagging likely sybil submissions to a contest whitelist
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    time::Duration,
+};
+
+use onomy_test_lib::super_orchestrator::stacked_errors::{Result, StackableErr};
+use serde::{Deserialize, Serialize};
+
+use crate::contest::Record;
+
+#[derive(Debug, Clone)]
+pub struct SybilOptions {
+    /// Keep only the earliest address submitted by each discord user
+    pub keep_earliest_per_user: bool,
+    /// The maximum edit distance between normalized usernames for them to be
+    /// considered near-identical, 0 disables the check
+    pub username_distance: usize,
+    /// Flag whenever at least `burst_count` submissions happen within
+    /// `burst_window`, 0 disables the check
+    pub burst_count: usize,
+    pub burst_window: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SybilFlag {
+    /// The discord user submitted `count` different addresses
+    MultipleAddresses { count: usize },
+    /// The username is near-identical to `other`
+    SimilarUsername { other: String },
+    /// The submission was part of a burst of `count` submissions
+    Burst { count: usize },
+}
+
+impl fmt::Display for SybilFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SybilFlag::MultipleAddresses { count } => {
+                write!(f, "user submitted {count} addresses")
+            }
+            SybilFlag::SimilarUsername { other } => write!(f, "username similar to \"{other}\""),
+            SybilFlag::Burst { count } => write!(f, "part of a burst of {count} submissions"),
+        }
+    }
+}
+
+/// A row of the review file written by `process_contest_whitelist.rs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRow {
+    pub row: usize,
+    pub timestamp: String,
+    pub discord_user: String,
+    pub addr: String,
+    pub flags: String,
+    /// If the record was kept in the whitelist output
+    pub kept: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SybilReport {
+    /// Flags by the row of the record
+    pub flags: BTreeMap<usize, BTreeSet<SybilFlag>>,
+    /// Rows removed by the policy, mapped to the row that was kept instead
+    pub removed: BTreeMap<usize, usize>,
+}
+
+impl SybilReport {
+    fn flag(&mut self, row: usize, flag: SybilFlag) {
+        self.flags.entry(row).or_default().insert(flag);
+    }
+
+    pub fn review_rows(&self, records: &[(usize, Record)]) -> Vec<ReviewRow> {
+        let mut rows = vec![];
+        for (row, record) in records {
+            if let Some(flags) = self.flags.get(row) {
+                rows.push(ReviewRow {
+                    row: *row,
+                    timestamp: record.timestamp.to_rfc3339(),
+                    discord_user: record.discord_user.clone(),
+                    addr: record.addr.clone(),
+                    flags: flags
+                        .iter()
+                        .map(|flag| flag.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                    kept: !self.removed.contains_key(row),
+                })
+            }
+        }
+        rows.sort_by_key(|review_row| review_row.row);
+        rows
+    }
+}
+
+/// Lowercases and removes all whitespace
+pub fn normalize_username(user: &str) -> String {
+    user.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Levenshtein distance over `char`s
+pub fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut prev: Vec<usize> = (0..=rhs.len()).collect();
+    let mut curr = vec![0; rhs.len() + 1];
+    for (i, l) in lhs.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, r) in rhs.iter().enumerate() {
+            let substitution = prev[j] + usize::from(l != *r);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[rhs.len()]
+}
+
+/// Analyzes `records`, which should be `(row, record)` pairs ordered from
+/// earliest to latest submission
+pub fn analyze(records: &[(usize, Record)], options: &SybilOptions) -> Result<SybilReport> {
+    let mut report = SybilReport::default();
+
+    // multiple addresses per user
+    let mut by_user: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (row, record) in records {
+        by_user
+            .entry(normalize_username(&record.discord_user))
+            .or_default()
+            .push(*row);
+    }
+    for rows in by_user.values() {
+        if rows.len() > 1 {
+            for row in rows {
+                report.flag(*row, SybilFlag::MultipleAddresses { count: rows.len() });
+            }
+            if options.keep_earliest_per_user {
+                for row in &rows[1..] {
+                    report.removed.insert(*row, rows[0]);
+                }
+            }
+        }
+    }
+
+    // near-identical usernames
+    if options.username_distance > 0 {
+        let users: Vec<&String> = by_user.keys().collect();
+        for (i, lhs) in users.iter().enumerate() {
+            for rhs in &users[(i + 1)..] {
+                if lhs.chars().count().abs_diff(rhs.chars().count()) > options.username_distance {
+                    continue
+                }
+                if edit_distance(lhs, rhs) <= options.username_distance {
+                    for row in &by_user[*lhs] {
+                        report.flag(*row, SybilFlag::SimilarUsername {
+                            other: (*rhs).clone(),
+                        });
+                    }
+                    for row in &by_user[*rhs] {
+                        report.flag(*row, SybilFlag::SimilarUsername {
+                            other: (*lhs).clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // timestamp bursts
+    if options.burst_count > 0 {
+        let mut times: Vec<_> = records
+            .iter()
+            .map(|(row, record)| (record.timestamp, *row))
+            .collect();
+        times.sort();
+        let window = chrono::Duration::from_std(options.burst_window).stack()?;
+        let mut burst_sizes: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut start = 0;
+        for end in 0..times.len() {
+            while (times[end].0 - times[start].0) > window {
+                start += 1;
+            }
+            let count = end + 1 - start;
+            if count >= options.burst_count {
+                for (_, row) in &times[start..=end] {
+                    let size = burst_sizes.entry(*row).or_default();
+                    *size = (*size).max(count);
+                }
+            }
+        }
+        for (row, count) in burst_sizes {
+            report.flag(row, SybilFlag::Burst { count });
+        }
+    }
+
+    Ok(report)
+}
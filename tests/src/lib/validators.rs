@@ -0,0 +1,403 @@
+//! Staking and slashing queries over gRPC, and the mapping of consumer
+//! consensus addresses to provider validators through ICS key assignment
+
+use std::collections::BTreeMap;
+
+use bech32::{ToBase32, Variant};
+use chrono::{DateTime, TimeZone, Utc};
+use cosmos_sdk_proto::cosmos::{
+    base::{
+        query::v1beta1::PageRequest,
+        tendermint::v1beta1::{service_client::ServiceClient, GetLatestValidatorSetRequest},
+    },
+    crypto::ed25519::PubKey,
+    slashing::v1beta1::{
+        query_client::QueryClient as SlashingQueryClient, QueryParamsRequest,
+        QuerySigningInfosRequest,
+    },
+    staking::v1beta1::{
+        query_client::QueryClient as StakingQueryClient, BondStatus,
+        QueryParamsRequest as StakingQueryParamsRequest, QueryValidatorDelegationsRequest,
+        QueryValidatorsRequest,
+    },
+};
+use onomy_test_lib::{
+    reprefix_bech32,
+    super_orchestrator::stacked_errors::{Error, Result, StackableErr},
+};
+use prost::Message;
+use sha2::Digest;
+
+use crate::ics::query_validator_consumer_addr;
+
+pub const ED25519_PUBKEY_URL: &str = "/cosmos.crypto.ed25519.PubKey";
+/// The most validators that CometBFT returns per page
+const CONSENSUS_PAGE_LIMIT: u64 = 100;
+
+fn page_request(key: Vec<u8>) -> Option<PageRequest> {
+    Some(PageRequest {
+        key,
+        offset: 0,
+        limit: 1000,
+        count_total: false,
+        reverse: false,
+    })
+}
+
+/// Returns the bech32 consensus address with the `{prefix}valcons` prefix of a
+/// `/cosmos.crypto.ed25519.PubKey`
+pub fn consensus_address(pubkey: &prost_types::Any, prefix: &str) -> Result<String> {
+    if pubkey.type_url != ED25519_PUBKEY_URL {
+        return Err(Error::from(format!(
+            "unsupported consensus key type {}",
+            pubkey.type_url
+        )))
+    }
+    let key = PubKey::decode(pubkey.value.as_slice()).stack()?.key;
+    let hash = sha2::Sha256::digest(&key);
+    bech32::encode(
+        &format!("{prefix}valcons"),
+        hash[..20].to_base32(),
+        Variant::Bech32,
+    )
+    .stack()
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidatorInfo {
+    pub operator_address: String,
+    pub moniker: String,
+    /// The `{prefix}valcons` address of the validator on its own chain
+    pub consensus_address: String,
+    pub status: BondStatus,
+    pub jailed: bool,
+    pub tokens: u128,
+}
+
+impl ValidatorInfo {
+    pub fn bonded(&self) -> bool {
+        self.status == BondStatus::Bonded
+    }
+}
+
+/// Queries every validator of the staking module, regardless of status
+pub async fn query_validators(grpc: &str, prefix: &str) -> Result<Vec<ValidatorInfo>> {
+    let mut client = StakingQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut validators = vec![];
+    let mut next_key = vec![];
+    loop {
+        let response = client
+            .validators(QueryValidatorsRequest {
+                status: String::new(),
+                pagination: page_request(next_key),
+            })
+            .await
+            .stack_err(|| format!("could not query the validators on {grpc}"))?
+            .into_inner();
+        for validator in response.validators {
+            let pubkey = validator.consensus_pubkey.as_ref().stack_err(|| {
+                format!(
+                    "validator {} has no consensus key",
+                    validator.operator_address
+                )
+            })?;
+            validators.push(ValidatorInfo {
+                consensus_address: consensus_address(pubkey, prefix).stack()?,
+                moniker: validator
+                    .description
+                    .map(|description| description.moniker)
+                    .unwrap_or_default(),
+                status: BondStatus::from_i32(validator.status).unwrap_or(BondStatus::Unspecified),
+                jailed: validator.jailed,
+                tokens: validator.tokens.parse().stack()?,
+                operator_address: validator.operator_address,
+            });
+        }
+        next_key = response
+            .pagination
+            .map(|page| page.next_key)
+            .unwrap_or_default();
+        if next_key.is_empty() {
+            break
+        }
+    }
+    Ok(validators)
+}
+
+/// Sums the tokens that each delegator address has delegated to `validators`
+pub async fn query_delegated_amounts(
+    grpc: &str,
+    validators: &[ValidatorInfo],
+) -> Result<BTreeMap<String, u128>> {
+    let mut client = StakingQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut amounts = BTreeMap::<String, u128>::new();
+    for validator in validators {
+        let mut next_key = vec![];
+        loop {
+            let response = client
+                .validator_delegations(QueryValidatorDelegationsRequest {
+                    validator_addr: validator.operator_address.clone(),
+                    pagination: page_request(next_key),
+                })
+                .await
+                .stack_err(|| {
+                    format!(
+                        "could not query the delegations to {} on {grpc}",
+                        validator.operator_address
+                    )
+                })?
+                .into_inner();
+            for response in response.delegation_responses {
+                let (delegation, balance) = match (response.delegation, response.balance) {
+                    (Some(delegation), Some(balance)) => (delegation, balance),
+                    _ => continue,
+                };
+                let tokens: u128 = balance.amount.parse().stack()?;
+                let total = amounts.entry(delegation.delegator_address).or_default();
+                *total = total
+                    .checked_add(tokens)
+                    .stack_err(|| "delegation overflow")?;
+            }
+            next_key = response
+                .pagination
+                .map(|page| page.next_key)
+                .unwrap_or_default();
+            if next_key.is_empty() {
+                break
+            }
+        }
+    }
+    Ok(amounts)
+}
+
+#[derive(Debug, Clone)]
+pub struct SigningInfo {
+    pub consensus_address: String,
+    pub missed_blocks_counter: u64,
+    pub jailed_until: Option<DateTime<Utc>>,
+    pub tombstoned: bool,
+}
+
+impl SigningInfo {
+    pub fn jailed_at(&self, now: DateTime<Utc>) -> bool {
+        self.jailed_until
+            .map_or(false, |jailed_until| jailed_until > now)
+    }
+}
+
+pub async fn query_signing_infos(grpc: &str) -> Result<Vec<SigningInfo>> {
+    let mut client = SlashingQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut infos = vec![];
+    let mut next_key = vec![];
+    loop {
+        let response = client
+            .signing_infos(QuerySigningInfosRequest {
+                pagination: page_request(next_key),
+            })
+            .await
+            .stack_err(|| format!("could not query the signing infos on {grpc}"))?
+            .into_inner();
+        for info in response.info {
+            infos.push(SigningInfo {
+                consensus_address: info.address,
+                missed_blocks_counter: u64::try_from(info.missed_blocks_counter).stack()?,
+                jailed_until: info.jailed_until.and_then(|time| {
+                    Utc.timestamp_opt(time.seconds, u32::try_from(time.nanos).ok()?)
+                        .single()
+                }),
+                tombstoned: info.tombstoned,
+            });
+        }
+        next_key = response
+            .pagination
+            .map(|page| page.next_key)
+            .unwrap_or_default();
+        if next_key.is_empty() {
+            break
+        }
+    }
+    Ok(infos)
+}
+
+/// `sdk.Dec`s are 18 decimal fixed point numbers
+pub const DEC_ONE: u128 = 1_000_000_000_000_000_000;
+
+/// Parses an `sdk.Dec` encoded as the bytes of its scaled integer, or of a
+/// decimal string on some versions
+pub fn parse_dec(bytes: &[u8]) -> Result<u128> {
+    let s = std::str::from_utf8(bytes).stack()?;
+    match s.split_once('.') {
+        Some((int, frac)) => {
+            if frac.len() > 18 {
+                return Err(Error::from(format!("too many decimals in {s}")))
+            }
+            let int: u128 = int.parse().stack()?;
+            let frac: u128 = format!("{frac:0<18}").parse().stack()?;
+            Ok(int * DEC_ONE + frac)
+        }
+        None => s.parse().stack(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlashingParams {
+    pub signed_blocks_window: u64,
+    /// `min_signed_per_window` scaled by 10^18
+    pub min_signed_per_window: u128,
+    pub downtime_jail_duration: std::time::Duration,
+}
+
+impl SlashingParams {
+    /// The number of missed blocks in the window that is still allowed, a
+    /// validator is jailed when it misses more than this. This rounds the same
+    /// way as the SDK, which uses banker's rounding for the minimum signed.
+    pub fn max_missed_blocks(&self) -> u64 {
+        let product = u128::from(self.signed_blocks_window) * self.min_signed_per_window;
+        let (mut min_signed, rem) = (product / DEC_ONE, product % DEC_ONE);
+        if (rem > DEC_ONE / 2) || ((rem == DEC_ONE / 2) && (min_signed % 2 == 1)) {
+            min_signed += 1;
+        }
+        self.signed_blocks_window
+            .saturating_sub(u64::try_from(min_signed).unwrap_or(u64::MAX))
+    }
+}
+
+pub async fn query_slashing_params(grpc: &str) -> Result<SlashingParams> {
+    let mut client = SlashingQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let params = client
+        .params(QueryParamsRequest {})
+        .await
+        .stack_err(|| format!("could not query the slashing params on {grpc}"))?
+        .into_inner()
+        .params
+        .stack_err(|| format!("{grpc} returned no slashing params"))?;
+    let jail_duration = params.downtime_jail_duration.unwrap_or_default();
+    Ok(SlashingParams {
+        signed_blocks_window: u64::try_from(params.signed_blocks_window).stack()?,
+        min_signed_per_window: parse_dec(&params.min_signed_per_window).stack()?,
+        downtime_jail_duration: std::time::Duration::new(
+            u64::try_from(jail_duration.seconds).stack()?,
+            u32::try_from(jail_duration.nanos).stack()?,
+        ),
+    })
+}
+
+/// Queries the `bond_denom` of the staking params
+pub async fn query_bond_denom(grpc: &str) -> Result<String> {
+    let mut client = StakingQueryClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    Ok(client
+        .params(StakingQueryParamsRequest {})
+        .await
+        .stack_err(|| format!("could not query the staking params on {grpc}"))?
+        .into_inner()
+        .params
+        .stack_err(|| format!("{grpc} returned no staking params"))?
+        .bond_denom)
+}
+
+/// A validator of the latest CometBFT validator set, on consumers this is the
+/// CCV validator set
+#[derive(Debug, Clone)]
+pub struct ConsensusValidator {
+    /// The `{prefix}valcons` address
+    pub address: String,
+    pub voting_power: i64,
+}
+
+/// Queries the latest CometBFT validator set. The SDK 0.45 service never
+/// returns a `next_key` and CometBFT serves at most 100 validators per page, so
+/// this pages by offset until `pagination.total` validators are collected.
+pub async fn query_consensus_validators(grpc: &str) -> Result<Vec<ConsensusValidator>> {
+    let mut client = ServiceClient::connect(grpc.to_owned())
+        .await
+        .stack_err(|| format!("could not connect to {grpc}"))?;
+    let mut validators = vec![];
+    loop {
+        let response = client
+            .get_latest_validator_set(GetLatestValidatorSetRequest {
+                pagination: Some(PageRequest {
+                    key: vec![],
+                    offset: u64::try_from(validators.len()).stack()?,
+                    limit: CONSENSUS_PAGE_LIMIT,
+                    count_total: true,
+                    reverse: false,
+                }),
+            })
+            .await
+            .stack_err(|| format!("could not query the validator set on {grpc}"))?
+            .into_inner();
+        if response.validators.is_empty() {
+            break
+        }
+        validators.extend(
+            response
+                .validators
+                .into_iter()
+                .map(|validator| ConsensusValidator {
+                    address: validator.address,
+                    voting_power: validator.voting_power,
+                }),
+        );
+        let total = response.pagination.map_or(0, |page| page.total);
+        if u64::try_from(validators.len()).stack()? >= total {
+            break
+        }
+    }
+    Ok(validators)
+}
+
+/// Returns the consumer consensus address that each of `validators` assigned
+/// on the consumer, or `None` for validators that use their provider key
+pub async fn query_assigned_consumer_keys(
+    provider_grpc: &str,
+    consumer_chain_id: &str,
+    validators: &[ValidatorInfo],
+) -> Result<Vec<Option<String>>> {
+    let mut keys = vec![];
+    for validator in validators {
+        keys.push(
+            query_validator_consumer_addr(
+                provider_grpc,
+                consumer_chain_id,
+                &validator.consensus_address,
+            )
+            .await
+            .stack()?,
+        );
+    }
+    Ok(keys)
+}
+
+/// Maps the consensus addresses that the provider validators use on the
+/// consumer to their index in `validators`. Validators that assigned a
+/// consumer key are found through the provider, the others use their provider
+/// key on the consumer.
+pub async fn consumer_address_map(
+    provider_grpc: &str,
+    consumer_chain_id: &str,
+    consumer_prefix: &str,
+    validators: &[ValidatorInfo],
+) -> Result<BTreeMap<String, usize>> {
+    let keys = query_assigned_consumer_keys(provider_grpc, consumer_chain_id, validators)
+        .await
+        .stack()?;
+    let mut map = BTreeMap::new();
+    for (i, (validator, key)) in validators.iter().zip(keys).enumerate() {
+        let consumer_address = key.unwrap_or_else(|| validator.consensus_address.clone());
+        map.insert(
+            reprefix_bech32(&consumer_address, &format!("{consumer_prefix}valcons")).stack()?,
+            i,
+        );
+    }
+    Ok(map)
+}